@@ -30,7 +30,46 @@ type UnitFields = Punctuated<syn::Field, Comma>;
 struct Field {
     hash: u64,
     field: TokenStream2,
-    callback: Option<Path>,
+    callback: Option<Callback>,
+}
+
+/// Function rendering a field in place of its `Content` implementation.
+enum Callback {
+    /// `#[ramhorns(callback = path)]`
+    Path(Path),
+    /// `#[ramhorns(md(options...))]`
+    Markdown(Vec<syn::Ident>),
+}
+
+/// Options accepted by `#[ramhorns(md(...))]`, the names of `MarkdownOptions` methods.
+const MARKDOWN_OPTIONS: &[&str] = &[
+    "tables",
+    "footnotes",
+    "strikethrough",
+    "tasklists",
+    "smart_punctuation",
+    "heading_attributes",
+    "autolinks",
+    "sanitize",
+    "highlight",
+];
+
+impl Callback {
+    fn call(&self, field: &TokenStream2) -> TokenStream2 {
+        match self {
+            Callback::Path(path) => quote!(#path(&self.#field, encoder)),
+            Callback::Markdown(options) if options.is_empty() => {
+                quote!(::ramhorns::encoding::encode_cmark(&self.#field, encoder))
+            }
+            Callback::Markdown(options) => quote! {
+                ::ramhorns::encoding::encode_cmark_with(
+                    &self.#field,
+                    encoder,
+                    ::ramhorns::MarkdownOptions::new() #( .#options() )*,
+                )
+            },
+        }
+    }
 }
 
 impl PartialEq for Field {
@@ -55,7 +94,7 @@ impl Ord for Field {
 
 struct Ramhorns {
     skip: bool,
-    md: bool,
+    md: Option<Vec<syn::Ident>>,
     flatten: bool,
     rename: Option<LitStr>,
     rename_all: Option<LitStr>,
@@ -80,7 +119,7 @@ impl syn::parse::Parse for Ramhorns {
     #[allow(unreachable_code, unused_imports, unused_variables)]
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let mut skip = false;
-        let mut md = false;
+        let mut md = None;
         let mut flatten = false;
         let mut rename = None;
         let mut rename_all = None;
@@ -91,7 +130,31 @@ impl syn::parse::Parse for Ramhorns {
 
             match &*attr_ident.to_string() {
                 "skip" => skip = true,
-                "md" => md = true,
+                "md" => {
+                    let mut options = Vec::new();
+
+                    if input.peek(syn::token::Paren) {
+                        let content;
+                        syn::parenthesized!(content in input);
+
+                        for option in Punctuated::<syn::Ident, Comma>::parse_terminated(&content)? {
+                            if !MARKDOWN_OPTIONS.contains(&&*option.to_string()) {
+                                return Err(syn::Error::new(
+                                    option.span(),
+                                    format!("Unknown markdown option `{option}`. Supported options are `{}`", MARKDOWN_OPTIONS.join("`, `")),
+                                ));
+                            }
+                            if option == "highlight" && !cfg!(feature = "syntect") {
+                                return Err(syn::Error::new(
+                                    option.span(),
+                                    "Markdown option `highlight` requires the `syntect` feature of ramhorns",
+                                ));
+                            }
+                            options.push(option);
+                        }
+                    }
+                    md = Some(options);
+                }
                 "flatten" => flatten = true,
                 "rename" => {
                     input.parse::<syn::Token![=]>()?;
@@ -209,7 +272,6 @@ pub fn content_derive(input: TokenStream) -> TokenStream {
     };
 
     let mut flatten = Vec::new();
-    let md_callback: Path = syn::parse(quote!(::ramhorns::encoding::encode_cmark).into()).unwrap();
     let mut fields = fields
         .enumerate()
         .filter_map(|(index, field)| {
@@ -221,8 +283,8 @@ pub fn content_derive(input: TokenStream) -> TokenStream {
                 Ok(Some(ramhorns)) => {
                     skip = ramhorns.skip;
 
-                    if ramhorns.md {
-                        callback = Some(md_callback.clone());
+                    if let Some(options) = ramhorns.md {
+                        callback = Some(Callback::Markdown(options));
                     }
 
                     if ramhorns.flatten {
@@ -242,7 +304,7 @@ pub fn content_derive(input: TokenStream) -> TokenStream {
                     }
 
                     if let Some(path) = ramhorns.callback {
-                        callback = Some(path);
+                        callback = Some(Callback::Path(path));
                     }
                 }
                 Ok(None) => (),
@@ -305,8 +367,9 @@ pub fn content_derive(input: TokenStream) -> TokenStream {
              ..
          }| {
             if let Some(callback) = callback {
+                let call = callback.call(field);
                 quote! {
-                    #hash => #callback(&self.#field, encoder).map(|_| true),
+                    #hash => #call.map(|_| true),
                 }
             } else {
                 quote! {
@@ -324,8 +387,9 @@ pub fn content_derive(input: TokenStream) -> TokenStream {
              ..
          }| {
             if let Some(callback) = callback {
+                let call = callback.call(field);
                 quote! {
-                    #hash => #callback(&self.#field, encoder).map(|_| true),
+                    #hash => #call.map(|_| true),
                 }
             } else {
                 quote! {
@@ -412,13 +412,13 @@ impl<T: Content> Content for Vec<T> {
 }
 
 #[cfg(feature = "indexes")]
-struct IndexBasedRender<'a, T> {
-    length: usize,
-    index: usize,
-    item: &'a T,
+pub(crate) struct IndexBasedRender<'a, T: ?Sized> {
+    pub length: usize,
+    pub index: usize,
+    pub item: &'a T,
 }
 #[cfg(feature = "indexes")]
-impl<T: Content> Content for IndexBasedRender<'_, T> {
+impl<T: Content + ?Sized> Content for IndexBasedRender<'_, T> {
     #[inline]
     fn is_truthy(&self) -> bool {
         true
@@ -568,88 +568,101 @@ impl<T: Content, const N: usize> Content for ArrayVec<T, N> {
     }
 }
 
-impl<K, V, S> Content for HashMap<K, V, S>
-where
-    K: Borrow<str> + Hash + Eq,
-    V: Content,
-    S: BuildHasher,
-{
-    fn is_truthy(&self) -> bool {
-        !self.is_empty()
-    }
+/// Implements the methods of `Content` for maps with string keys, which render
+/// sections with themselves and have their fields looked up by name with `get`.
+macro_rules! impl_map_methods {
+    () => {
+        fn is_truthy(&self) -> bool {
+            !self.is_empty()
+        }
 
-    /// Render a section with self.
-    #[inline]
-    fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
-    where
-        C: ContentSequence,
-        E: Encoder,
-    {
-        if self.is_truthy() {
-            section.with(self).render(encoder)
-        } else {
-            Ok(())
+        /// Render a section with self.
+        #[inline]
+        fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
+        where
+            C: ContentSequence,
+            E: Encoder,
+        {
+            if self.is_truthy() {
+                section.with(self).render(encoder)
+            } else {
+                Ok(())
+            }
         }
-    }
 
-    fn render_field_escaped<E>(&self, _: u64, name: &str, encoder: &mut E) -> Result<bool, E::Error>
-    where
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_escaped(encoder).map(|_| true),
-            None => Ok(false),
+        fn render_field_escaped<E>(
+            &self,
+            _: u64,
+            name: &str,
+            encoder: &mut E,
+        ) -> Result<bool, E::Error>
+        where
+            E: Encoder,
+        {
+            match self.get(name) {
+                Some(v) => v.render_escaped(encoder).map(|_| true),
+                None => Ok(false),
+            }
         }
-    }
 
-    fn render_field_unescaped<E>(
-        &self,
-        _: u64,
-        name: &str,
-        encoder: &mut E,
-    ) -> Result<bool, E::Error>
-    where
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_unescaped(encoder).map(|_| true),
-            None => Ok(false),
+        fn render_field_unescaped<E>(
+            &self,
+            _: u64,
+            name: &str,
+            encoder: &mut E,
+        ) -> Result<bool, E::Error>
+        where
+            E: Encoder,
+        {
+            match self.get(name) {
+                Some(v) => v.render_unescaped(encoder).map(|_| true),
+                None => Ok(false),
+            }
         }
-    }
 
-    fn render_field_section<C, E>(
-        &self,
-        _: u64,
-        name: &str,
-        section: Section<C>,
-        encoder: &mut E,
-    ) -> Result<bool, E::Error>
-    where
-        C: ContentSequence,
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_section(section, encoder).map(|_| true),
-            None => Ok(false),
+        fn render_field_section<C, E>(
+            &self,
+            _: u64,
+            name: &str,
+            section: Section<C>,
+            encoder: &mut E,
+        ) -> Result<bool, E::Error>
+        where
+            C: ContentSequence,
+            E: Encoder,
+        {
+            match self.get(name) {
+                Some(v) => v.render_section(section, encoder).map(|_| true),
+                None => Ok(false),
+            }
         }
-    }
 
-    fn render_field_inverse<C, E>(
-        &self,
-        _: u64,
-        name: &str,
-        section: Section<C>,
-        encoder: &mut E,
-    ) -> Result<bool, E::Error>
-    where
-        C: ContentSequence,
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_inverse(section, encoder).map(|_| true),
-            None => Ok(false),
+        fn render_field_inverse<C, E>(
+            &self,
+            _: u64,
+            name: &str,
+            section: Section<C>,
+            encoder: &mut E,
+        ) -> Result<bool, E::Error>
+        where
+            C: ContentSequence,
+            E: Encoder,
+        {
+            match self.get(name) {
+                Some(v) => v.render_inverse(section, encoder).map(|_| true),
+                None => Ok(false),
+            }
         }
-    }
+    };
+}
+
+impl<K, V, S> Content for HashMap<K, V, S>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: Content,
+    S: BuildHasher,
+{
+    impl_map_methods!();
 }
 
 impl<K, V> Content for BTreeMap<K, V>
@@ -657,82 +670,7 @@ where
     K: Borrow<str> + Ord,
     V: Content,
 {
-    fn is_truthy(&self) -> bool {
-        !self.is_empty()
-    }
-
-    /// Render a section with self.
-    #[inline]
-    fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
-    where
-        C: ContentSequence,
-        E: Encoder,
-    {
-        if self.is_truthy() {
-            section.with(self).render(encoder)
-        } else {
-            Ok(())
-        }
-    }
-
-    fn render_field_escaped<E>(&self, _: u64, name: &str, encoder: &mut E) -> Result<bool, E::Error>
-    where
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_escaped(encoder).map(|_| true),
-            None => Ok(false),
-        }
-    }
-
-    fn render_field_unescaped<E>(
-        &self,
-        _: u64,
-        name: &str,
-        encoder: &mut E,
-    ) -> Result<bool, E::Error>
-    where
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_unescaped(encoder).map(|_| true),
-            None => Ok(false),
-        }
-    }
-
-    fn render_field_section<C, E>(
-        &self,
-        _: u64,
-        name: &str,
-        section: Section<C>,
-        encoder: &mut E,
-    ) -> Result<bool, E::Error>
-    where
-        C: ContentSequence,
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_section(section, encoder).map(|_| true),
-            None => Ok(false),
-        }
-    }
-
-    fn render_field_inverse<C, E>(
-        &self,
-        _: u64,
-        name: &str,
-        section: Section<C>,
-        encoder: &mut E,
-    ) -> Result<bool, E::Error>
-    where
-        C: ContentSequence,
-        E: Encoder,
-    {
-        match self.get(name) {
-            Some(v) => v.render_inverse(section, encoder).map(|_| true),
-            None => Ok(false),
-        }
-    }
+    impl_map_methods!();
 }
 
 macro_rules! impl_pointer_types {
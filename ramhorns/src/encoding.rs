@@ -7,9 +7,13 @@
 
 use std::fmt;
 use std::io;
+use std::time::{Duration, Instant};
 
-#[cfg(feature = "pulldown-cmark")]
-use pulldown_cmark::{html, Event, Parser};
+use crate::search::Needles;
+use crate::{Escape, Escaper};
+
+#[cfg(feature = "markdown")]
+use crate::markdown::{DefaultBackend, MarkdownBackend, MarkdownOptions};
 
 /// A trait that wraps around either a `String` or `std::io::Write`, providing UTF-8 safe
 /// writing boundaries and special HTML character escaping.
@@ -23,43 +27,380 @@ pub trait Encoder {
     /// Write a `&str` to this `Encoder`, escaping special HTML characters.
     fn write_escaped(&mut self, part: &str) -> Result<(), Self::Error>;
 
-    #[cfg(feature = "pulldown-cmark")]
-    /// Write HTML from an `Iterator` of `pulldown_cmark` `Event`s.
-    fn write_html<'a, I: Iterator<Item = Event<'a>>>(&mut self, iter: I)
-        -> Result<(), Self::Error>;
+    /// Write a `&str` to this `Encoder`, escaping it for the given context of
+    /// an HTML document. Text is escaped with [`write_escaped`](#tymethod.write_escaped).
+    fn write_escaped_in(&mut self, part: &str, context: EscapeContext) -> Result<(), Self::Error> {
+        match context {
+            EscapeContext::Text => self.write_escaped(part),
+            context => escape_in(part, context, |part| self.write_unescaped(part)),
+        }
+    }
+
+    /// Write HTML rendered ahead of time, such as Markdown rendered by a
+    /// [`MarkdownBackend`](../trait.MarkdownBackend.html). Writes it as is
+    /// unless overridden.
+    #[inline]
+    fn write_html(&mut self, html: &str) -> Result<(), Self::Error> {
+        self.write_unescaped(html)
+    }
 
     /// Write a `Display` implementor to this `Encoder` in plain mode.
     fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error>;
 
     /// Write a `Display` implementor to this `Encoder`, escaping special HTML characters.
     fn format_escaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error>;
+
+    /// Called before each block of a template is rendered, once for every time
+    /// it's rendered. Does nothing unless overridden, eg: by a
+    /// [`BudgetEncoder`](./struct.BudgetEncoder.html).
+    #[inline]
+    fn visit_block(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called before a section is rendered, once for every time it's rendered,
+    /// such as for each item of a collection. Does nothing unless overridden.
+    #[inline]
+    fn visit_iteration(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
-/// Local helper for escaping stuff into strings.
-struct EscapingStringEncoder<'a>(&'a mut String);
+/// Context of an HTML document a tag is interpolated into, determining how its
+/// value has to be escaped to be safe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum EscapeContext {
+    /// Text between HTML tags, eg: `<p>{{text}}</p>`.
+    #[default]
+    Text,
+    /// Quoted attribute value, eg: `<p title="{{title}}">` or `<p title='{{title}}'>`.
+    Attribute,
+    /// Unquoted attribute value or the inside of a tag, eg: `<p title={{title}}>`.
+    UnquotedAttribute,
+    /// Start of an attribute value containing a URL, eg: `<a href="{{url}}">`.
+    /// URLs with schemes other than `http`, `https`, `mailto` and `tel` are replaced
+    /// with `about:invalid`.
+    Url,
+    /// JavaScript in a `<script>` element or an event handler attribute,
+    /// eg: `<script>var name = "{{name}}";</script>`.
+    Script,
+    /// CSS in a `<style>` element or a `style` attribute,
+    /// eg: `<p style="color: {{color}}">`.
+    Style,
+}
 
-impl<'a> EscapingStringEncoder<'a> {
-    /// Write with escaping special HTML characters. Since we are dealing
-    /// with a String, we don't need to return a `Result`.
-    fn write_escaped(&mut self, part: &str) {
-        let mut start = 0;
+/// URL replacing URLs with unsafe schemes.
+pub(crate) const INVALID_URL: &str = "about:invalid";
+
+/// Escape `part` for the given context, calling `write` with escaped chunks.
+pub(crate) fn escape_in<E, W>(part: &str, context: EscapeContext, mut write: W) -> Result<(), E>
+where
+    W: FnMut(&str) -> Result<(), E>,
+{
+    if context == EscapeContext::Url && !is_safe_url(part) {
+        return write(INVALID_URL);
+    }
 
-        for (idx, byte) in part.bytes().enumerate() {
-            let replace = match byte {
-                b'<' => "&lt;",
-                b'>' => "&gt;",
-                b'&' => "&amp;",
-                b'"' => "&quot;",
+    let mut start = 0;
+    let mut buf = [0; 8];
+
+    for (idx, ch) in part.char_indices() {
+        let replace: &str = match (context, ch) {
+            (_, 'a'..='z' | 'A'..='Z' | '0'..='9') => continue,
+            (EscapeContext::Script, '\u{2028}') => "\\u2028",
+            (EscapeContext::Script, '\u{2029}') => "\\u2029",
+            (_, ch) if !ch.is_ascii() => continue,
+            (EscapeContext::Attribute | EscapeContext::Text, ch) => match ch {
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '&' => "&amp;",
+                '"' => "&quot;",
+                '\'' => "&#x27;",
+                '`' => "&#x60;",
+                _ => continue,
+            },
+            (EscapeContext::Url, ch) => match ch {
+                '&' => "&amp;",
+                '"' | '\'' | '<' | '>' | '`' | ' ' => hex(&mut buf, "%", ch, ""),
+                ch if ch.is_ascii_control() => hex(&mut buf, "%", ch, ""),
                 _ => continue,
-            };
+            },
+            (EscapeContext::UnquotedAttribute, ',' | '.' | '-' | '_') => continue,
+            (EscapeContext::UnquotedAttribute, ch) => hex(&mut buf, "&#x", ch, ";"),
+            (EscapeContext::Script, ',' | '.' | '_') => continue,
+            (EscapeContext::Script, ch) => hex(&mut buf, "\\x", ch, ""),
+            (EscapeContext::Style, ',' | '.' | '-' | '_' | '#' | '%') => continue,
+            (EscapeContext::Style, ch) => hex(&mut buf, "\\", ch, " "),
+        };
+
+        write(&part[start..idx])?;
+        write(replace)?;
+
+        start = idx + ch.len_utf8();
+    }
+
+    write(&part[start..])
+}
+
+/// Format an ASCII character as two hex digits between `prefix` and `suffix`.
+pub(crate) fn hex<'a>(buf: &'a mut [u8; 8], prefix: &str, ch: char, suffix: &str) -> &'a str {
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+    let byte = ch as u8;
+    let len = prefix.len() + 2 + suffix.len();
+
+    buf[..prefix.len()].copy_from_slice(prefix.as_bytes());
+    buf[prefix.len()] = DIGITS[(byte >> 4) as usize];
+    buf[prefix.len() + 1] = DIGITS[(byte & 0xF) as usize];
+    buf[prefix.len() + 2..len].copy_from_slice(suffix.as_bytes());
+
+    // Only ASCII has been written
+    std::str::from_utf8(&buf[..len]).unwrap_or_default()
+}
+
+/// Check that a URL is relative or uses a scheme that can't execute code.
+pub(crate) fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore leading whitespace, and tabs or newlines anywhere.
+    let url = url.trim_start_matches(|ch: char| ch <= ' ');
+    let end = match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => end,
+        _ => return true,
+    };
+    let scheme = url[..end]
+        .bytes()
+        .filter(|b| !matches!(b, b'\t' | b'\n' | b'\r'));
+
+    ["http", "https", "mailto", "tel"].iter().any(|safe| {
+        let mut scheme = scheme.clone();
+
+        safe.bytes()
+            .all(|b| scheme.next().map(|s| s.to_ascii_lowercase()) == Some(b))
+            && scheme.next().is_none()
+    })
+}
+
+/// Wrapper escaping all values for an `EscapeContext` other than text.
+pub(crate) struct ContextEncoder<'a, E> {
+    inner: &'a mut E,
+    context: EscapeContext,
+}
+
+impl<'a, E: Encoder> ContextEncoder<'a, E> {
+    #[inline]
+    pub fn new(inner: &'a mut E, context: EscapeContext) -> Self {
+        ContextEncoder { inner, context }
+    }
+}
+
+impl<E: Encoder> Encoder for ContextEncoder<'_, E> {
+    type Error = E::Error;
+
+    #[inline]
+    fn write_unescaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        self.inner.write_unescaped(part)
+    }
+
+    fn write_escaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        let context = self.context;
+
+        // Only the start of a URL needs to be checked for its scheme
+        if context == EscapeContext::Url {
+            self.context = EscapeContext::UnquotedAttribute;
+        }
+        self.inner.write_escaped_in(part, context)
+    }
+
+    #[inline]
+    fn write_html(&mut self, html: &str) -> Result<(), Self::Error> {
+        self.inner.write_html(html)
+    }
+
+    #[inline]
+    fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        self.inner.format_unescaped(display)
+    }
+
+    fn format_escaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        self.write_escaped(&display.to_string())
+    }
+
+    #[inline]
+    fn visit_block(&mut self) -> Result<(), Self::Error> {
+        self.inner.visit_block()
+    }
+
+    #[inline]
+    fn visit_iteration(&mut self) -> Result<(), Self::Error> {
+        self.inner.visit_iteration()
+    }
+}
+
+/// Table of replacements for ASCII characters, used for escaping values in HTML text.
+///
+/// [`EscapeTable::HTML`](#associatedconstant.HTML) is used by the built-in encoders.
+/// Other tables can be used as an [`Escaper`](../trait.Escaper.html), or through
+/// [`Escape::Table`](../enum.Escape.html#variant.Table).
+/// ```
+/// # use ramhorns::encoding::EscapeTable;
+/// # use ramhorns::{Content, Escape, Template};
+/// const TABLE: EscapeTable = EscapeTable::STRICT.with(b'/', "&#x2F;");
+///
+/// #[derive(Content)]
+/// struct Post<'a> {
+///     title: &'a str,
+/// }
+///
+/// let post = Post { title: "<It's `a=b/c`>" };
+/// let strict = Template::new("<p>{{title}}</p>")
+///     .unwrap()
+///     .with_escape(Escape::Table(&EscapeTable::STRICT));
+/// let custom = Template::new("<p>{{title}}</p>")
+///     .unwrap()
+///     .with_escape(Escape::Table(&TABLE));
+///
+/// assert_eq!(
+///     strict.render(&post),
+///     "<p>&lt;It&#x27;s &#x60;a&#x3D;b/c&#x60;&gt;</p>"
+/// );
+/// assert_eq!(
+///     custom.render(&post),
+///     "<p>&lt;It&#x27;s &#x60;a&#x3D;b&#x2F;c&#x60;&gt;</p>"
+/// );
+/// ```
+#[derive(Clone, Copy)]
+pub struct EscapeTable {
+    replacements: [Option<&'static str>; 128],
+    needles: Needles,
+}
+
+impl EscapeTable {
+    /// Table escaping `<`, `>`, `&` and `"`, sufficient for text between HTML tags
+    /// and double-quoted attributes.
+    pub const HTML: EscapeTable = EscapeTable::empty()
+        .with(b'<', "&lt;")
+        .with(b'>', "&gt;")
+        .with(b'&', "&amp;")
+        .with(b'"', "&quot;");
+
+    /// Table additionally escaping `'`, `` ` `` and `=`, as recommended by
+    /// [OWASP](https://cheatsheetseries.owasp.org/cheatsheets/Cross_Site_Scripting_Prevention_Cheat_Sheet.html)
+    /// for attributes.
+    pub const STRICT: EscapeTable = EscapeTable::HTML
+        .with(b'\'', "&#x27;")
+        .with(b'`', "&#x60;")
+        .with(b'=', "&#x3D;");
+
+    /// Table that doesn't escape anything.
+    pub const fn empty() -> Self {
+        EscapeTable {
+            replacements: [None; 128],
+            needles: Needles::new(),
+        }
+    }
+
+    /// Replace the ASCII character `byte` with `replacement`.
+    ///
+    /// # Panics
+    /// Panics if `byte` isn't ASCII.
+    pub const fn with(mut self, byte: u8, replacement: &'static str) -> Self {
+        assert!(byte.is_ascii(), "only ASCII characters can be escaped");
+
+        self.replacements[byte as usize] = Some(replacement);
+        self.needles = self.needles.with(byte);
+        self
+    }
+
+    /// Stop escaping the ASCII character `byte`.
+    pub const fn without(mut self, byte: u8) -> Self {
+        if byte.is_ascii() {
+            self.replacements[byte as usize] = None;
+            self.needles = self.needles.without(byte);
+        }
+        self
+    }
+
+    /// Replacement for the given byte, if it's escaped.
+    #[inline]
+    pub fn get(&self, byte: u8) -> Option<&'static str> {
+        *self.replacements.get(byte as usize)?
+    }
+
+    /// Escape `part`, passing the escaped output to `write` in chunks.
+    #[inline]
+    pub fn escape<E, W>(&self, part: &str, mut write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        let mut start = 0;
+
+        while let Some(idx) = self.needles.find(part.as_bytes(), start) {
+            // Only ASCII characters are replaced, so `idx` is on a char boundary
+            write(&part[start..idx])?;
+            write(self.replacements[part.as_bytes()[idx] as usize].unwrap_or_default())?;
+
+            start = idx + 1;
+        }
+
+        write(&part[start..])
+    }
+
+    /// Same as [`escape`](#method.escape), but dealing with byte slices.
+    #[inline]
+    pub fn escape_bytes<E, W>(&self, part: &[u8], mut write: W) -> Result<(), E>
+    where
+        W: FnMut(&[u8]) -> Result<(), E>,
+    {
+        let mut start = 0;
 
-            self.0.push_str(&part[start..idx]);
-            self.0.push_str(replace);
+        while let Some(idx) = self.needles.find(part, start) {
+            let replace = self.replacements[part[idx] as usize].unwrap_or_default();
+
+            write(&part[start..idx])?;
+            write(replace.as_bytes())?;
 
             start = idx + 1;
         }
 
-        self.0.push_str(&part[start..]);
+        write(&part[start..])
+    }
+}
+
+impl fmt::Debug for EscapeTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries((0..128u8).filter_map(|byte| Some((byte as char, self.get(byte)?))))
+            .finish()
+    }
+}
+
+impl PartialEq for EscapeTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.replacements == other.replacements
+    }
+}
+
+impl Eq for EscapeTable {}
+
+impl Default for EscapeTable {
+    fn default() -> Self {
+        EscapeTable::HTML
+    }
+}
+
+/// Local helper for escaping stuff into strings.
+struct EscapingStringEncoder<'a>(&'a mut String);
+
+impl<'a> EscapingStringEncoder<'a> {
+    /// Write with escaping special HTML characters. Since we are dealing
+    /// with a String, we don't need to return a `Result`.
+    fn write_escaped(&mut self, part: &str) {
+        let buf = &mut *self.0;
+
+        let _ = EscapeTable::HTML.escape(part, |part| {
+            buf.push_str(part);
+            Ok::<(), NeverError>(())
+        });
     }
 }
 
@@ -75,37 +416,31 @@ impl<'a> fmt::Write for EscapingStringEncoder<'a> {
 
 /// Encoder wrapper around io::Write. We can't implement `Encoder` on a generic here,
 /// because we're implementing it directly for `String`.
-pub(crate) struct EscapingIOEncoder<W: io::Write> {
+///
+/// Note that everything written through its own `io::Write` implementation is escaped.
+pub struct EscapingIOEncoder<W: io::Write> {
     inner: W,
 }
 
 impl<W: io::Write> EscapingIOEncoder<W> {
+    /// Wrap the `io::Write` implementor.
     #[inline]
     pub fn new(inner: W) -> Self {
         Self { inner }
     }
 
+    /// Unwrap the inner `io::Write` implementor.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
     /// Same as `EscapingStringEncoder`, but dealing with byte arrays and writing to
     /// the inner `io::Write`.
     fn write_escaped_bytes(&mut self, part: &[u8]) -> io::Result<()> {
-        let mut start = 0;
-
-        for (idx, byte) in part.iter().enumerate() {
-            let replace: &[u8] = match *byte {
-                b'<' => b"&lt;",
-                b'>' => b"&gt;",
-                b'&' => b"&amp;",
-                b'"' => b"&quot;",
-                _ => continue,
-            };
-
-            self.inner.write_all(&part[start..idx])?;
-            self.inner.write_all(replace)?;
-
-            start = idx + 1;
-        }
+        let inner = &mut self.inner;
 
-        self.inner.write_all(&part[start..])
+        EscapeTable::HTML.escape_bytes(part, |part| inner.write_all(part))
     }
 }
 
@@ -141,12 +476,6 @@ impl<W: io::Write> Encoder for EscapingIOEncoder<W> {
         self.write_escaped_bytes(part.as_bytes())
     }
 
-    #[cfg(feature = "pulldown-cmark")]
-    #[inline]
-    fn write_html<'a, I: Iterator<Item = Event<'a>>>(&mut self, iter: I) -> io::Result<()> {
-        html::write_html_io(&mut self.inner, iter)
-    }
-
     #[inline]
     fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
         write!(self.inner, "{}", display)
@@ -160,10 +489,665 @@ impl<W: io::Write> Encoder for EscapingIOEncoder<W> {
     }
 }
 
+/// Encoder wrapper around any `fmt::Write`, such as a `fmt::Formatter`,
+/// escaping the same way as rendering to a `String`.
+/// ```
+/// # use ramhorns::encoding::EscapingFmtEncoder;
+/// # use ramhorns::{Content, Template};
+/// #[derive(Content)]
+/// struct Post<'a> {
+///     title: &'a str,
+/// }
+///
+/// let tpl = Template::new("<h1>{{title}}</h1>").unwrap();
+/// let mut encoder = EscapingFmtEncoder::new(String::new());
+///
+/// tpl.render_with_encoder(&mut encoder, &Post { title: "Fish & Chips" }).unwrap();
+///
+/// assert_eq!(encoder.into_inner(), "<h1>Fish &amp; Chips</h1>");
+/// ```
+pub struct EscapingFmtEncoder<W: fmt::Write> {
+    inner: W,
+}
+
+impl<W: fmt::Write> EscapingFmtEncoder<W> {
+    /// Wrap the `fmt::Write` implementor.
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Unwrap the inner `fmt::Write` implementor.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// `fmt::Write` adapter escaping everything written to the inner writer,
+/// so `format_escaped` can stream without allocating.
+struct EscapingFmtWriter<'a, W>(&'a mut W);
+
+impl<W: fmt::Write> fmt::Write for EscapingFmtWriter<'_, W> {
+    #[inline]
+    fn write_str(&mut self, part: &str) -> fmt::Result {
+        let inner = &mut *self.0;
+
+        EscapeTable::HTML.escape(part, |part| inner.write_str(part))
+    }
+}
+
+impl<W: fmt::Write> Encoder for EscapingFmtEncoder<W> {
+    type Error = fmt::Error;
+
+    #[inline]
+    fn write_unescaped(&mut self, part: &str) -> fmt::Result {
+        self.inner.write_str(part)
+    }
+
+    #[inline]
+    fn write_escaped(&mut self, part: &str) -> fmt::Result {
+        let inner = &mut self.inner;
+
+        EscapeTable::HTML.escape(part, |part| inner.write_str(part))
+    }
+
+    #[inline]
+    fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> fmt::Result {
+        write!(self.inner, "{}", display)
+    }
+
+    #[inline]
+    fn format_escaped<D: fmt::Display>(&mut self, display: D) -> fmt::Result {
+        use std::fmt::Write;
+
+        write!(EscapingFmtWriter(&mut self.inner), "{}", display)
+    }
+}
+
+/// Encoder for a `tokio::io::AsyncWrite`. Rendering is synchronous, so the output
+/// is collected in a buffer, and written to the inner writer with
+/// [`flush`](#method.flush), instead of awaiting on every block.
+///
+/// The buffer holds up to 8 KiB by default, see [`with_capacity`](#method.with_capacity).
+/// Once it's full, as much of it as the inner writer accepts without waiting is
+/// written out while rendering. Only what the writer can't take right away, eg:
+/// when a peer doesn't read fast enough, stays buffered past the capacity until
+/// the next `flush`.
+/// ```
+/// # use ramhorns::encoding::AsyncWriteEncoder;
+/// # use ramhorns::{Content, Template};
+/// #[derive(Content)]
+/// struct Post<'a> {
+///     title: &'a str,
+/// }
+///
+/// # async fn render(socket: &mut tokio::io::DuplexStream) -> std::io::Result<()> {
+/// let tpl = Template::new("<h1>{{title}}</h1>").unwrap();
+/// let mut encoder = AsyncWriteEncoder::new(socket);
+///
+/// tpl.render_with_encoder(&mut encoder, &Post { title: "Hello" })?;
+/// tpl.render_with_encoder(&mut encoder, &Post { title: "World" })?;
+///
+/// encoder.flush().await
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub struct AsyncWriteEncoder<W> {
+    inner: W,
+    buf: Vec<u8>,
+    capacity: usize,
+}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> AsyncWriteEncoder<W> {
+    /// Wrap the `AsyncWrite` implementor, with a buffer of 8 KiB.
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(inner, 8 * 1024)
+    }
+
+    /// Wrap the `AsyncWrite` implementor, writing the output out whenever
+    /// `capacity` bytes have been buffered.
+    #[inline]
+    pub fn with_capacity(inner: W, capacity: usize) -> Self {
+        AsyncWriteEncoder {
+            inner,
+            buf: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Number of bytes rendered, but not written to the inner writer yet.
+    #[inline]
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Write everything rendered so far to the inner writer, and flush it.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        self.inner.write_all(&self.buf).await?;
+        self.buf.clear();
+        self.inner.flush().await
+    }
+
+    /// Unwrap the inner `AsyncWrite` implementor, discarding anything that
+    /// hasn't been written yet.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Once the buffer is full, write out as much of it as the inner writer
+    /// accepts without waiting.
+    fn write_out(&mut self) -> io::Result<()> {
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Waker};
+
+        if self.buf.len() < self.capacity {
+            return Ok(());
+        }
+
+        // Nothing waits on this context, whatever is left is written by `flush`
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut written = 0;
+
+        let result = loop {
+            if written == self.buf.len() {
+                break Ok(());
+            }
+            match Pin::new(&mut self.inner).poll_write(&mut cx, &self.buf[written..]) {
+                Poll::Ready(Ok(0)) => break Err(io::ErrorKind::WriteZero.into()),
+                Poll::Ready(Ok(n)) => written += n,
+                Poll::Ready(Err(err)) => break Err(err),
+                Poll::Pending => break Ok(()),
+            }
+        };
+        self.buf.drain(..written);
+
+        result
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> Encoder for AsyncWriteEncoder<W> {
+    type Error = io::Error;
+
+    #[inline]
+    fn write_unescaped(&mut self, part: &str) -> io::Result<()> {
+        self.buf.extend_from_slice(part.as_bytes());
+        self.write_out()
+    }
+
+    #[inline]
+    fn write_escaped(&mut self, part: &str) -> io::Result<()> {
+        EscapingIOEncoder::new(&mut self.buf).write_escaped(part)?;
+        self.write_out()
+    }
+
+    #[inline]
+    fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> io::Result<()> {
+        use std::io::Write;
+
+        write!(self.buf, "{}", display)?;
+        self.write_out()
+    }
+
+    #[inline]
+    fn format_escaped<D: fmt::Display>(&mut self, display: D) -> io::Result<()> {
+        EscapingIOEncoder::new(&mut self.buf).format_escaped(display)?;
+        self.write_out()
+    }
+}
+
+/// Error of a [`LimitedEncoder`](./struct.LimitedEncoder.html).
+#[derive(Debug)]
+pub enum LimitError<E> {
+    /// Rendering was aborted, as the output would exceed the limit of bytes.
+    Exceeded {
+        /// The limit of bytes that was exceeded.
+        limit: usize,
+    },
+    /// The wrapped encoder failed.
+    Encoder(E),
+}
+
+impl<E: fmt::Display> fmt::Display for LimitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::Exceeded { limit } => {
+                write!(f, "Rendered output exceeds the limit of {} bytes", limit)
+            }
+            LimitError::Encoder(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for LimitError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LimitError::Exceeded { .. } => None,
+            LimitError::Encoder(err) => Some(err),
+        }
+    }
+}
+
+/// Encoder wrapper aborting rendering with
+/// [`LimitError::Exceeded`](./enum.LimitError.html#variant.Exceeded) once the
+/// output would exceed a limit of bytes, for templates from untrusted sources.
+///
+/// Values are escaped for HTML by the `LimitedEncoder` itself, the same way the
+/// built-in encoders escape them, and passed on to the wrapped encoder as they are,
+/// so that the limit applies to the escaped output. Templates with another
+/// [`Escape`](../enum.Escape.html) hand their values over escaped already.
+/// ```
+/// # use ramhorns::encoding::{EscapingIOEncoder, LimitError, LimitedEncoder};
+/// # use ramhorns::{Content, Template};
+/// #[derive(Content, Clone)]
+/// struct Post {
+///     title: &'static str,
+/// }
+///
+/// #[derive(Content)]
+/// struct Feed {
+///     posts: Vec<Post>,
+/// }
+///
+/// let tpl = Template::new("{{#posts}}<p>{{title}}</p>{{/posts}}").unwrap();
+/// let feed = Feed { posts: vec![Post { title: "Hello" }; 1000] };
+///
+/// let mut encoder = LimitedEncoder::new(String::new(), 1024);
+/// assert!(matches!(
+///     tpl.render_with_encoder(&mut encoder, &feed),
+///     Err(LimitError::Exceeded { limit: 1024 })
+/// ));
+///
+/// let mut encoder = LimitedEncoder::new(EscapingIOEncoder::new(Vec::new()), 1 << 20);
+/// tpl.render_with_encoder(&mut encoder, &feed).unwrap();
+/// assert_eq!(encoder.written(), 12000);
+/// ```
+pub struct LimitedEncoder<E> {
+    inner: E,
+    limit: usize,
+    written: usize,
+}
+
+impl<E: Encoder> LimitedEncoder<E> {
+    /// Wrap the `encoder`, allowing at most `limit` bytes to be written.
+    #[inline]
+    pub fn new(encoder: E, limit: usize) -> Self {
+        LimitedEncoder {
+            inner: encoder,
+            limit,
+            written: 0,
+        }
+    }
+
+    /// Number of bytes counted so far.
+    #[inline]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Unwrap the inner encoder.
+    #[inline]
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Count `len` bytes about to be written, unless they exceed the limit.
+    fn count(&mut self, len: usize) -> Result<(), LimitError<E::Error>> {
+        if len > self.limit - self.written {
+            return Err(LimitError::Exceeded { limit: self.limit });
+        }
+        self.written += len;
+
+        Ok(())
+    }
+}
+
+/// `fmt::Write` adapter counting the bytes of a formatted value, without
+/// writing them anywhere.
+#[derive(Default)]
+struct Counter(usize);
+
+impl Counter {
+    fn count<D: fmt::Display>(display: &D) -> usize {
+        use std::fmt::Write;
+
+        let mut counter = Counter::default();
+        let _ = write!(counter, "{}", display);
+        counter.0
+    }
+}
+
+impl fmt::Write for Counter {
+    #[inline]
+    fn write_str(&mut self, part: &str) -> fmt::Result {
+        self.0 += part.len();
+
+        Ok(())
+    }
+}
+
+impl<E: Encoder> Encoder for LimitedEncoder<E> {
+    type Error = LimitError<E::Error>;
+
+    #[inline]
+    fn write_unescaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        self.count(part.len())?;
+        self.inner
+            .write_unescaped(part)
+            .map_err(LimitError::Encoder)
+    }
+
+    #[inline]
+    fn write_escaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        Escape::Html.escape(part, |part| self.write_unescaped(part))
+    }
+
+    #[inline]
+    fn write_escaped_in(&mut self, part: &str, context: EscapeContext) -> Result<(), Self::Error> {
+        Escape::Html.escape_in(part, context, |part| self.write_unescaped(part))
+    }
+
+    #[inline]
+    fn write_html(&mut self, html: &str) -> Result<(), Self::Error> {
+        self.count(html.len())?;
+        self.inner.write_html(html).map_err(LimitError::Encoder)
+    }
+
+    #[inline]
+    fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        self.count(Counter::count(&display))?;
+        self.inner
+            .format_unescaped(display)
+            .map_err(LimitError::Encoder)
+    }
+
+    #[inline]
+    fn format_escaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        self.write_escaped(&display.to_string())
+    }
+
+    #[inline]
+    fn visit_block(&mut self) -> Result<(), Self::Error> {
+        self.inner.visit_block().map_err(LimitError::Encoder)
+    }
+
+    #[inline]
+    fn visit_iteration(&mut self) -> Result<(), Self::Error> {
+        self.inner.visit_iteration().map_err(LimitError::Encoder)
+    }
+}
+
+/// Limits on the work done rendering a template, for templates from untrusted
+/// sources. Enforced by a [`BudgetEncoder`](./struct.BudgetEncoder.html).
+///
+/// Nothing is limited unless set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderBudget {
+    blocks: Option<usize>,
+    iterations: Option<usize>,
+    deadline: Option<Instant>,
+}
+
+impl RenderBudget {
+    /// Create a new budget, without any limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `blocks` blocks to be rendered, counting every time a block
+    /// is rendered again inside of a section.
+    pub fn blocks(mut self, blocks: usize) -> Self {
+        self.blocks = Some(blocks);
+        self
+    }
+
+    /// Allow sections to be rendered at most `iterations` times in total,
+    /// counting every item of a collection they're rendered for.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Abort rendering after the `deadline`. The clock is only checked every
+    /// 256 blocks and iterations, so rendering might run slightly past it.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Abort rendering once `timeout` has elapsed from now.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+}
+
+/// Error of a [`BudgetEncoder`](./struct.BudgetEncoder.html).
+#[derive(Debug)]
+pub enum BudgetError<E> {
+    /// Rendering was aborted, as more blocks would be rendered than allowed.
+    Blocks {
+        /// The limit of blocks that was exceeded.
+        limit: usize,
+    },
+    /// Rendering was aborted, as sections would be iterated more times than allowed.
+    Iterations {
+        /// The limit of iterations that was exceeded.
+        limit: usize,
+    },
+    /// Rendering was aborted, as the deadline has passed.
+    Deadline,
+    /// The wrapped encoder failed.
+    Encoder(E),
+}
+
+impl<E: fmt::Display> fmt::Display for BudgetError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetError::Blocks { limit } => {
+                write!(f, "Rendering exceeds the limit of {} blocks", limit)
+            }
+            BudgetError::Iterations { limit } => {
+                write!(
+                    f,
+                    "Rendering exceeds the limit of {} section iterations",
+                    limit
+                )
+            }
+            BudgetError::Deadline => f.write_str("Rendering exceeds its deadline"),
+            BudgetError::Encoder(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for BudgetError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BudgetError::Encoder(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// How many blocks and iterations are rendered between checks of the deadline,
+/// must be a power of two.
+const DEADLINE_INTERVAL: usize = 256;
+
+/// Encoder wrapper aborting rendering with a [`BudgetError`](./enum.BudgetError.html)
+/// once it exceeds its [`RenderBudget`](./struct.RenderBudget.html), so that templates
+/// from untrusted sources can't keep rendering unbounded.
+/// ```
+/// # use ramhorns::encoding::{BudgetEncoder, BudgetError, RenderBudget};
+/// # use ramhorns::{Content, Template};
+/// #[derive(Content, Clone)]
+/// struct Cell {
+///     value: u32,
+/// }
+///
+/// #[derive(Content, Clone)]
+/// struct Row {
+///     cells: Vec<Cell>,
+/// }
+///
+/// #[derive(Content)]
+/// struct Table {
+///     rows: Vec<Row>,
+/// }
+///
+/// let tpl = Template::new("{{#rows}}<tr>{{#cells}}<td>{{value}}</td>{{/cells}}</tr>{{/rows}}")
+///     .unwrap();
+/// let row = Row { cells: (0..100).map(|value| Cell { value }).collect() };
+/// let table = Table { rows: vec![row; 100] };
+///
+/// let mut encoder = BudgetEncoder::new(String::new(), RenderBudget::new().iterations(1000));
+/// assert!(matches!(
+///     tpl.render_with_encoder(&mut encoder, &table),
+///     Err(BudgetError::Iterations { limit: 1000 })
+/// ));
+///
+/// let mut encoder = BudgetEncoder::new(String::new(), RenderBudget::new().iterations(20000));
+/// tpl.render_with_encoder(&mut encoder, &table).unwrap();
+/// assert_eq!(encoder.iterations(), 10100);
+/// ```
+pub struct BudgetEncoder<E> {
+    inner: E,
+    budget: RenderBudget,
+    blocks: usize,
+    iterations: usize,
+}
+
+impl<E: Encoder> BudgetEncoder<E> {
+    /// Wrap the `encoder`, limiting rendering to the `budget`.
+    #[inline]
+    pub fn new(encoder: E, budget: RenderBudget) -> Self {
+        BudgetEncoder {
+            inner: encoder,
+            budget,
+            blocks: 0,
+            iterations: 0,
+        }
+    }
+
+    /// Number of blocks rendered so far.
+    #[inline]
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// Number of section iterations rendered so far.
+    #[inline]
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Unwrap the inner encoder.
+    #[inline]
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Check the deadline, if any, every `DEADLINE_INTERVAL` steps.
+    #[inline]
+    fn check_deadline(&self) -> Result<(), BudgetError<E::Error>> {
+        match self.budget.deadline {
+            Some(deadline)
+                if (self.blocks + self.iterations) & (DEADLINE_INTERVAL - 1) == 0
+                    && Instant::now() >= deadline =>
+            {
+                Err(BudgetError::Deadline)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<E: Encoder> Encoder for BudgetEncoder<E> {
+    type Error = BudgetError<E::Error>;
+
+    #[inline]
+    fn write_unescaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        self.inner
+            .write_unescaped(part)
+            .map_err(BudgetError::Encoder)
+    }
+
+    #[inline]
+    fn write_escaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        self.inner.write_escaped(part).map_err(BudgetError::Encoder)
+    }
+
+    #[inline]
+    fn write_escaped_in(&mut self, part: &str, context: EscapeContext) -> Result<(), Self::Error> {
+        self.inner
+            .write_escaped_in(part, context)
+            .map_err(BudgetError::Encoder)
+    }
+
+    #[inline]
+    fn write_html(&mut self, html: &str) -> Result<(), Self::Error> {
+        self.inner.write_html(html).map_err(BudgetError::Encoder)
+    }
+
+    #[inline]
+    fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        self.inner
+            .format_unescaped(display)
+            .map_err(BudgetError::Encoder)
+    }
+
+    #[inline]
+    fn format_escaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        self.inner
+            .format_escaped(display)
+            .map_err(BudgetError::Encoder)
+    }
+
+    fn visit_block(&mut self) -> Result<(), Self::Error> {
+        if let Some(limit) = self.budget.blocks {
+            if self.blocks >= limit {
+                return Err(BudgetError::Blocks { limit });
+            }
+        }
+        self.check_deadline()?;
+        self.blocks += 1;
+        self.inner.visit_block().map_err(BudgetError::Encoder)
+    }
+
+    fn visit_iteration(&mut self) -> Result<(), Self::Error> {
+        if let Some(limit) = self.budget.iterations {
+            if self.iterations >= limit {
+                return Err(BudgetError::Iterations { limit });
+            }
+        }
+        self.check_deadline()?;
+        self.iterations += 1;
+        self.inner.visit_iteration().map_err(BudgetError::Encoder)
+    }
+}
+
 /// Error type for `String`, impossible to instantiate.
 /// Rust optimizes `Result<(), NeverError>` to 0-size.
 pub enum NeverError {}
 
+impl fmt::Debug for NeverError {
+    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {}
+    }
+}
+
+impl fmt::Display for NeverError {
+    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {}
+    }
+}
+
+impl std::error::Error for NeverError {}
+
 impl Encoder for String {
     // Change this to `!` once stabilized.
     type Error = NeverError;
@@ -182,17 +1166,6 @@ impl Encoder for String {
         Ok(())
     }
 
-    #[cfg(feature = "pulldown-cmark")]
-    #[inline]
-    fn write_html<'a, I: Iterator<Item = Event<'a>>>(
-        &mut self,
-        iter: I,
-    ) -> Result<(), Self::Error> {
-        html::push_html(self, iter);
-
-        Ok(())
-    }
-
     #[inline]
     fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
         use std::fmt::Write;
@@ -214,10 +1187,35 @@ impl Encoder for String {
     }
 }
 
-#[cfg(feature = "pulldown-cmark")]
-/// Parse and encode the markdown using pulldown_cmark
+#[cfg(feature = "markdown")]
+/// Parse and encode the markdown with the default backend
 pub fn encode_cmark<E: Encoder>(source: &str, encoder: &mut E) -> Result<(), E::Error> {
-    let parser = Parser::new(source);
+    encode_cmark_with(source, encoder, MarkdownOptions::new())
+}
+
+#[cfg(feature = "markdown")]
+/// Parse and encode the markdown with the default backend, with the given extensions enabled
+pub fn encode_cmark_with<E: Encoder>(
+    source: &str,
+    encoder: &mut E,
+    options: MarkdownOptions,
+) -> Result<(), E::Error> {
+    encode_markdown::<DefaultBackend, E>(source, encoder, options)
+}
+
+#[cfg(feature = "markdown")]
+/// Parse and encode the markdown with the given backend and extensions
+///
+/// The HTML is rendered to a `String` first and then passed to
+/// [`Encoder::write_html`](./trait.Encoder.html#method.write_html), so it isn't
+/// streamed to the writer of an `io::Write` encoder as it's being rendered.
+pub fn encode_markdown<B: MarkdownBackend, E: Encoder>(
+    source: &str,
+    encoder: &mut E,
+    options: MarkdownOptions,
+) -> Result<(), E::Error> {
+    let mut html = String::with_capacity(source.len() * 3 / 2);
 
-    encoder.write_html(parser)
+    B::render(source, options, &mut html);
+    encoder.write_html(&html)
 }
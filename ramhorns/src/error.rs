@@ -33,12 +33,59 @@ pub enum Error {
     /// The template file with the given name was not found
     NotFound(Box<str>),
 
+    /// Error in the template with the given name, which might have been
+    /// included as a partial by other templates.
+    InTemplate {
+        /// Name of the template in which the error occurred.
+        name: Box<str>,
+        /// Names of the templates including it, starting with the outermost one.
+        include_chain: Vec<Box<str>>,
+        /// The error that occurred.
+        source: Box<Error>,
+    },
+
+    /// Multiple templates failed to load
+    Multiple(Vec<Error>),
+
     /// Failed to parse the section index
     #[cfg(feature = "indexes")]
     IndexParse(String),
 }
 
-impl error::Error for Error {}
+impl Error {
+    /// Attach the name of the template being loaded to this error.
+    pub(crate) fn in_template(self, template: &str) -> Self {
+        match self {
+            Error::InTemplate {
+                name,
+                mut include_chain,
+                source,
+            } => {
+                include_chain.insert(0, template.into());
+                Error::InTemplate {
+                    name,
+                    include_chain,
+                    source,
+                }
+            }
+            source => Error::InTemplate {
+                name: template.into(),
+                include_chain: Vec::new(),
+                source: Box::new(source),
+            },
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::InTemplate { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
@@ -76,6 +123,25 @@ impl fmt::Display for Error {
                 name
             ),
             Error::NotFound(name) => write!(f, "Template file {} not found", name),
+            Error::InTemplate {
+                name,
+                include_chain,
+                source,
+            } => {
+                write!(f, "In template {}", name)?;
+                for (i, parent) in include_chain.iter().rev().enumerate() {
+                    let prefix = if i == 0 { "included from" } else { "from" };
+                    write!(f, ", {} {}", prefix, parent)?;
+                }
+                write!(f, ": {}", source)
+            }
+            Error::Multiple(errors) => {
+                write!(f, "{} templates failed to load", errors.len())?;
+                for error in errors {
+                    write!(f, "\n{}", error)?;
+                }
+                Ok(())
+            }
             #[cfg(feature = "indexes")]
             Error::IndexParse(index) => write!(f, "Failed to parse index {}", index),
         }
@@ -97,4 +163,30 @@ mod test {
             "Couldn't find closing braces matching opening braces"
         );
     }
+
+    #[test]
+    fn displays_include_chain() {
+        let error = Error::UnclosedTag
+            .in_template("footer.html")
+            .in_template("layout.html")
+            .in_template("page.html");
+
+        assert_eq!(
+            error.to_string(),
+            "In template footer.html, included from layout.html, from page.html: \
+             Couldn't find closing braces matching opening braces"
+        );
+
+        match error {
+            Error::InTemplate {
+                name,
+                include_chain,
+                ..
+            } => {
+                assert_eq!(&*name, "footer.html");
+                assert_eq!(include_chain, ["page.html".into(), "layout.html".into()]);
+            }
+            _ => panic!("Expected Error::InTemplate"),
+        }
+    }
 }
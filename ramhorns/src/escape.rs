@@ -0,0 +1,325 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+
+use crate::encoding::{escape_in, hex, Encoder, EscapeContext, EscapeTable};
+
+/// Strategy for escaping the values of `{{escaped}}` tags, for templates that
+/// don't produce HTML.
+pub trait Escaper {
+    /// Escape `part`, passing the escaped output to `write` in one or more chunks.
+    fn escape<E, W>(&self, part: &str, write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>;
+
+    /// Escape `part` interpolated into the given context of an HTML document.
+    /// Unless overridden, the context is ignored.
+    fn escape_in<E, W>(&self, part: &str, context: EscapeContext, write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        let _ = context;
+
+        self.escape(part, write)
+    }
+}
+
+/// Built-in escaping strategies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Escape {
+    /// Escape special HTML characters, according to the context of each tag.
+    #[default]
+    Html,
+    /// Leave values as they are, eg: for plain text emails.
+    None,
+    /// Escape values for the inside of a JSON string.
+    Json,
+    /// Escape special XML characters.
+    Xml,
+    /// Quote values containing commas, quotes or line breaks, as a CSV field.
+    Csv,
+    /// Escape LaTeX special characters.
+    Latex,
+    /// Quote values as a single shell word.
+    Shell,
+    /// Escape special HTML characters using the given table for text, eg:
+    /// [`EscapeTable::STRICT`](./encoding/struct.EscapeTable.html#associatedconstant.STRICT).
+    /// Other contexts are escaped the same as with `Html`.
+    Table(&'static EscapeTable),
+}
+
+impl Escaper for Escape {
+    fn escape<E, W>(&self, part: &str, mut write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        match self {
+            Escape::Html => EscapeTable::HTML.escape(part, write),
+            Escape::Table(table) => table.escape(part, write),
+            Escape::None => write(part),
+            Escape::Json => replace(part, write, |ch, buf| match ch {
+                '"' => Some("\\\""),
+                '\\' => Some("\\\\"),
+                '\n' => Some("\\n"),
+                '\r' => Some("\\r"),
+                '\t' => Some("\\t"),
+                '\u{2028}' => Some("\\u2028"),
+                '\u{2029}' => Some("\\u2029"),
+                ch if ch.is_ascii_control() => Some(hex(buf, "\\u00", ch, "")),
+                _ => None,
+            }),
+            Escape::Xml => replace(part, write, |ch, _| match ch {
+                '<' => Some("&lt;"),
+                '>' => Some("&gt;"),
+                '&' => Some("&amp;"),
+                '"' => Some("&quot;"),
+                '\'' => Some("&apos;"),
+                _ => None,
+            }),
+            Escape::Csv => {
+                if !part.contains([',', '"', '\r', '\n']) {
+                    return write(part);
+                }
+                write("\"")?;
+                replace(part, &mut write, |ch, _| match ch {
+                    '"' => Some("\"\""),
+                    _ => None,
+                })?;
+                write("\"")
+            }
+            Escape::Latex => replace(part, write, |ch, _| match ch {
+                '\\' => Some("\\textbackslash{}"),
+                '~' => Some("\\textasciitilde{}"),
+                '^' => Some("\\textasciicircum{}"),
+                '&' => Some("\\&"),
+                '%' => Some("\\%"),
+                '$' => Some("\\$"),
+                '#' => Some("\\#"),
+                '_' => Some("\\_"),
+                '{' => Some("\\{"),
+                '}' => Some("\\}"),
+                _ => None,
+            }),
+            Escape::Shell => {
+                write("'")?;
+                replace(part, &mut write, |ch, _| match ch {
+                    '\'' => Some("'\\''"),
+                    _ => None,
+                })?;
+                write("'")
+            }
+        }
+    }
+
+    fn escape_in<E, W>(&self, part: &str, context: EscapeContext, write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        match self {
+            Escape::Html | Escape::Table(_) if context != EscapeContext::Text => {
+                escape_in(part, context, write)
+            }
+            _ => self.escape(part, write),
+        }
+    }
+}
+
+impl Escaper for EscapeTable {
+    #[inline]
+    fn escape<E, W>(&self, part: &str, write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        EscapeTable::escape(self, part, write)
+    }
+
+    fn escape_in<E, W>(&self, part: &str, context: EscapeContext, write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        match context {
+            EscapeContext::Text => EscapeTable::escape(self, part, write),
+            context => escape_in(part, context, write),
+        }
+    }
+}
+
+impl<S: Escaper + ?Sized> Escaper for &S {
+    #[inline]
+    fn escape<E, W>(&self, part: &str, write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        S::escape(self, part, write)
+    }
+
+    #[inline]
+    fn escape_in<E, W>(&self, part: &str, context: EscapeContext, write: W) -> Result<(), E>
+    where
+        W: FnMut(&str) -> Result<(), E>,
+    {
+        S::escape_in(self, part, context, write)
+    }
+}
+
+/// Write `part`, replacing characters for which `replacement` returns a string.
+/// The buffer can be used to format replacements.
+#[inline]
+fn replace<E, W>(
+    part: &str,
+    mut write: W,
+    replacement: fn(char, &mut [u8; 8]) -> Option<&str>,
+) -> Result<(), E>
+where
+    W: FnMut(&str) -> Result<(), E>,
+{
+    let mut start = 0;
+    let mut buf = [0; 8];
+
+    for (idx, ch) in part.char_indices() {
+        if let Some(replace) = replacement(ch, &mut buf) {
+            write(&part[start..idx])?;
+            write(replace)?;
+
+            start = idx + ch.len_utf8();
+        }
+    }
+
+    write(&part[start..])
+}
+
+/// `Encoder` wrapper escaping values with an [`Escaper`](./trait.Escaper.html)
+/// instead of the HTML escaping of the wrapped `Encoder`.
+/// ```
+/// # use ramhorns::{Content, Escape, Escaping, Template};
+/// #[derive(Content)]
+/// struct Script<'a> {
+///     greeting: &'a str,
+/// }
+///
+/// let tpl = Template::new("echo {{greeting}}").unwrap();
+/// let mut script = String::new();
+///
+/// let _ = tpl.render_with_encoder(
+///     &mut Escaping::new(&mut script, Escape::Shell),
+///     &Script { greeting: "It's me" },
+/// );
+///
+/// assert_eq!(script, "echo 'It'\\''s me'");
+/// ```
+pub struct Escaping<'a, E, S> {
+    inner: &'a mut E,
+    escaper: S,
+}
+
+impl<'a, E: Encoder, S: Escaper> Escaping<'a, E, S> {
+    /// Wrap the `encoder`, escaping values with the `escaper`.
+    #[inline]
+    pub fn new(encoder: &'a mut E, escaper: S) -> Self {
+        Escaping {
+            inner: encoder,
+            escaper,
+        }
+    }
+}
+
+impl<E: Encoder, S: Escaper> Encoder for Escaping<'_, E, S> {
+    type Error = E::Error;
+
+    #[inline]
+    fn write_unescaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        self.inner.write_unescaped(part)
+    }
+
+    #[inline]
+    fn write_escaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        let inner = &mut *self.inner;
+
+        self.escaper
+            .escape(part, |part| inner.write_unescaped(part))
+    }
+
+    #[inline]
+    fn write_escaped_in(&mut self, part: &str, context: EscapeContext) -> Result<(), Self::Error> {
+        let inner = &mut *self.inner;
+
+        self.escaper
+            .escape_in(part, context, |part| inner.write_unescaped(part))
+    }
+
+    #[inline]
+    fn write_html(&mut self, html: &str) -> Result<(), Self::Error> {
+        self.inner.write_html(html)
+    }
+
+    #[inline]
+    fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        self.inner.format_unescaped(display)
+    }
+
+    #[inline]
+    fn format_escaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        // Quoting escapers need to see the whole value at once
+        self.write_escaped(&display.to_string())
+    }
+
+    #[inline]
+    fn visit_block(&mut self) -> Result<(), Self::Error> {
+        self.inner.visit_block()
+    }
+
+    #[inline]
+    fn visit_iteration(&mut self) -> Result<(), Self::Error> {
+        self.inner.visit_iteration()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn escape(escape: Escape, part: &str) -> String {
+        let mut buf = String::new();
+        let _ = escape.escape(part, |part| {
+            buf.push_str(part);
+            Ok::<(), ()>(())
+        });
+        buf
+    }
+
+    #[test]
+    fn escapes_values() {
+        let value = "It's \"50% off\" <now>, {a_b} & \\ more\n";
+
+        assert_eq!(
+            escape(Escape::Html, value),
+            "It's &quot;50% off&quot; &lt;now&gt;, {a_b} &amp; \\ more\n"
+        );
+        assert_eq!(escape(Escape::None, value), value);
+        assert_eq!(
+            escape(Escape::Json, value),
+            "It's \\\"50% off\\\" <now>, {a_b} & \\\\ more\\n"
+        );
+        assert_eq!(
+            escape(Escape::Xml, value),
+            "It&apos;s &quot;50% off&quot; &lt;now&gt;, {a_b} &amp; \\ more\n"
+        );
+        assert_eq!(
+            escape(Escape::Csv, value),
+            "\"It's \"\"50% off\"\" <now>, {a_b} & \\ more\n\""
+        );
+        assert_eq!(escape(Escape::Csv, "plain value"), "plain value");
+        assert_eq!(
+            escape(Escape::Latex, value),
+            "It's \"50\\% off\" <now>, \\{a\\_b\\} \\& \\textbackslash{} more\n"
+        );
+        assert_eq!(
+            escape(Escape::Shell, value),
+            "'It'\\''s \"50% off\" <now>, {a_b} & \\ more\n'"
+        );
+        assert_eq!(escape(Escape::Json, "\u{1}"), "\\u0001");
+    }
+}
@@ -0,0 +1,274 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::HashSet;
+use std::hash::BuildHasher;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use beef::Cow;
+
+use crate::{template_name, Error, Ramhorns};
+
+/// Policy for symbolic links encountered while loading a folder.
+///
+/// Dangling links are skipped with every policy, and folders that were already
+/// loaded, such as an ancestor a link points back to, aren't loaded again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Symlinks {
+    /// Follow all symbolic links, wherever they point to.
+    #[default]
+    Follow,
+    /// Follow symbolic links only if they point inside the templates folder.
+    FollowInside,
+    /// Ignore symbolic links altogether.
+    Skip,
+}
+
+/// Options controlling which files are loaded by
+/// [`Ramhorns::from_folder_with_options`](./struct.Ramhorns.html#method.from_folder_with_options).
+///
+/// Glob patterns are matched against template names, that is paths relative to the
+/// templates folder using `/` as separator. `*` and `?` match any characters but `/`,
+/// while `**` also matches across folders.
+///
+/// ```no_run
+/// # use ramhorns::{FolderOptions, Ramhorns};
+/// let options = FolderOptions::new()
+///     .extension("html")
+///     .extension("mustache")
+///     .exclude("_wip/**")
+///     .exclude("**/node_modules")
+///     .skip_hidden(true);
+///
+/// let tpls: Ramhorns = Ramhorns::from_folder_with_options("./templates", &options).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FolderOptions {
+    extensions: Vec<Box<str>>,
+    include: Vec<Box<str>>,
+    exclude: Vec<Box<str>>,
+    skip_hidden: bool,
+    symlinks: Symlinks,
+    collect_errors: bool,
+}
+
+impl FolderOptions {
+    /// Create new options. Unless other extensions are added, `.html` files are loaded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load files with the given extension, can be used multiple times.
+    pub fn extension(mut self, extension: &str) -> Self {
+        self.extensions.push(extension.into());
+        self
+    }
+
+    /// Only load templates with names matching the glob pattern, or any of the
+    /// patterns if used multiple times.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Don't load templates, or descend into folders, with names matching the
+    /// glob pattern. Can be used multiple times.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Skip files and folders with names starting with a `.`.
+    pub fn skip_hidden(mut self, skip: bool) -> Self {
+        self.skip_hidden = skip;
+        self
+    }
+
+    /// Set the policy for symbolic links, by default all links are followed.
+    pub fn symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Keep loading other files when a template fails to load. Once the whole
+    /// folder has been loaded, all errors are returned as
+    /// [`Error::Multiple`](./enum.Error.html#variant.Multiple).
+    ///
+    /// Templates that loaded successfully are kept when extending an existing
+    /// collection with
+    /// [`extend_from_folder_with_options`](./struct.Ramhorns.html#method.extend_from_folder_with_options).
+    /// To get them along with the errors when creating a new collection, use
+    /// [`from_folder_with_errors`](./struct.Ramhorns.html#method.from_folder_with_errors)
+    /// instead, which always collects errors.
+    pub fn collect_errors(mut self, collect: bool) -> Self {
+        self.collect_errors = collect;
+        self
+    }
+
+    fn has_extension(&self, path: &Path) -> bool {
+        let extension = match path.extension() {
+            Some(extension) => extension,
+            None => return false,
+        };
+
+        if self.extensions.is_empty() {
+            return extension == "html";
+        }
+        self.extensions.iter().any(|e| extension == &**e)
+    }
+
+    fn is_included(&self, name: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|pattern| glob(pattern, name))
+    }
+
+    fn is_excluded(&self, name: &str) -> bool {
+        self.exclude.iter().any(|pattern| glob(pattern, name))
+    }
+}
+
+impl<H: BuildHasher + Default> Ramhorns<H> {
+    pub(crate) fn load_folder(&mut self, dir: &Path, options: &FolderOptions) -> Result<(), Error> {
+        let errors = self.collect_folder(dir, options)?;
+
+        match errors.len() {
+            0 => Ok(()),
+            _ => Err(Error::Multiple(errors)),
+        }
+    }
+
+    /// Load the folder, returning errors of templates that failed to load if
+    /// they are being collected.
+    pub(crate) fn collect_folder(
+        &mut self,
+        dir: &Path,
+        options: &FolderOptions,
+    ) -> Result<Vec<Error>, Error> {
+        let mut errors = Vec::new();
+        let mut visited = HashSet::new();
+
+        visited.insert(dir.canonicalize()?);
+        self.walk_folder(dir, options, &mut visited, &mut errors)?;
+
+        Ok(errors)
+    }
+
+    fn walk_folder(
+        &mut self,
+        dir: &Path,
+        options: &FolderOptions,
+        visited: &mut HashSet<PathBuf>,
+        errors: &mut Vec<Error>,
+    ) -> Result<(), Error> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if options.skip_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let name = template_name(path.strip_prefix(&self.dir).unwrap_or(&path));
+
+            if entry.file_type()?.is_symlink() {
+                if options.symlinks == Symlinks::Skip {
+                    continue;
+                }
+
+                let target = match path.canonicalize() {
+                    Ok(target) => target,
+                    // Dangling links are skipped whatever the policy
+                    Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                    Err(err) if options.collect_errors => {
+                        errors.push(Error::from(err).in_template(&name));
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+
+                if options.symlinks == Symlinks::FollowInside && !target.starts_with(&self.dir) {
+                    continue;
+                }
+            }
+
+            if options.is_excluded(&name) {
+                continue;
+            }
+
+            if path.is_dir() {
+                // Links to a folder that was already walked, such as an ancestor
+                if visited.insert(path.canonicalize()?) {
+                    self.walk_folder(&path, options, visited, errors)?;
+                }
+            } else if options.has_extension(&path)
+                && options.is_included(&name)
+                && !self.partials.contains_key(name.as_str())
+            {
+                match self.load_internal(&path, Cow::owned(name)) {
+                    Err(err) if options.collect_errors => errors.push(err),
+                    result => result?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Match a template name against a glob pattern.
+pub(crate) fn glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    matches(&pattern, &name)
+}
+
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        // Any number of whole folders, including none
+        ['*', '*', '/', rest @ ..] => {
+            matches(rest, name)
+                || (0..name.len()).any(|i| name[i] == '/' && matches(rest, &name[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+        ['*', rest @ ..] => (0..=name.len())
+            .take_while(|&i| i == 0 || name[i - 1] != '/')
+            .any(|i| matches(rest, &name[i..])),
+        ['?', rest @ ..] => match name {
+            [c, tail @ ..] if *c != '/' => matches(rest, tail),
+            _ => false,
+        },
+        [p, rest @ ..] => match name {
+            [c, tail @ ..] if c == p => matches(rest, tail),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::glob;
+
+    #[test]
+    fn glob_matches_names() {
+        assert!(glob("*.html", "post.html"));
+        assert!(!glob("*.html", "blog/post.html"));
+        assert!(glob("blog/*.html", "blog/post.html"));
+        assert!(glob("blog/post.???l", "blog/post.html"));
+        assert!(!glob("blog/?post.html", "blog/post.html"));
+    }
+
+    #[test]
+    fn glob_matches_across_folders() {
+        assert!(glob("**/*.html", "post.html"));
+        assert!(glob("**/*.html", "blog/2024/post.html"));
+        assert!(glob("_wip/**", "_wip/draft.html"));
+        assert!(glob("_wip/**", "_wip/nested/draft.html"));
+        assert!(!glob("_wip/**", "blog/_wip/draft.html"));
+        assert!(glob("**/node_modules", "node_modules"));
+        assert!(glob("**/node_modules", "theme/node_modules"));
+        assert!(glob("blog/**.html", "blog/2024/post.html"));
+        assert!(!glob("**/node_modules", "theme/node_modules_backup"));
+    }
+}
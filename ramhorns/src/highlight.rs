@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::sync::OnceLock;
+
+use syntect::highlighting::ThemeSet;
+use syntect::html::{
+    css_for_theme_with_class_style, highlighted_html_for_string, ClassStyle, ClassedHTMLGenerator,
+};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::encoding::Encoder;
+
+/// How fenced code blocks in Markdown are highlighted, using the grammars and
+/// themes bundled with [`syntect`](https://docs.rs/syntect).
+///
+/// Code blocks are highlighted if the first word of their info string names
+/// a known language, eg: `rust` or `py`, others are rendered as usual.
+/// ```
+/// # use ramhorns::{Content, Highlight, MarkdownOptions, Template};
+/// #[derive(Content)]
+/// struct Post<'a> {
+///     #[ramhorns(md(highlight))]
+///     body: &'a str,
+/// }
+///
+/// let tpl = Template::new("{{body}}").unwrap();
+/// let html = tpl.render(&Post { body: "```rust\nlet x = 1;\n```" });
+///
+/// assert!(html.starts_with("<pre><code class=\"language-rust\"><span class=\"source rust\">"));
+///
+/// // Stylesheet for the classes
+/// let css = Highlight::css("InspiredGitHub").unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Highlight {
+    /// Wrap tokens in `<span>`s with CSS classes of their scopes, eg:
+    /// `<span class="keyword control rust">`, see [`Highlight::css`](#method.css).
+    Classes,
+    /// Style tokens inline with the bundled theme of the given name, eg:
+    /// `"InspiredGitHub"` or `"base16-ocean.dark"`. Code blocks are rendered
+    /// as usual if there is no such theme.
+    Theme(&'static str),
+}
+
+impl Highlight {
+    /// Stylesheet for [`Highlight::Classes`](#variant.Classes) with the bundled
+    /// theme of the given name, if it exists.
+    pub fn css(theme: &str) -> Option<String> {
+        let theme = themes().themes.get(theme)?;
+
+        css_for_theme_with_class_style(theme, ClassStyle::Spaced).ok()
+    }
+
+    /// HTML for `code` highlighted as `syntax`, if it can be highlighted.
+    fn render(self, code: &str, syntax: &SyntaxReference, language: &str) -> Option<String> {
+        match self {
+            Highlight::Classes => {
+                let mut generator = ClassedHTMLGenerator::new_with_class_style(
+                    syntax,
+                    syntaxes(),
+                    ClassStyle::Spaced,
+                );
+
+                for line in LinesWithEndings::from(code) {
+                    generator
+                        .parse_html_for_line_which_includes_newline(line)
+                        .ok()?;
+                }
+
+                let mut html = String::with_capacity(code.len() * 4);
+                let _ = html.write_unescaped("<pre><code class=\"language-");
+                let _ = html.write_escaped(language);
+                let _ = html.write_unescaped("\">");
+                let _ = html.write_unescaped(&generator.finalize());
+                let _ = html.write_unescaped("</code></pre>\n");
+
+                Some(html)
+            }
+            Highlight::Theme(theme) => {
+                let theme = themes().themes.get(theme)?;
+
+                highlighted_html_for_string(code, syntaxes(), syntax, theme).ok()
+            }
+        }
+    }
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// HTML for a fenced code block with the given info string, or `None` if it
+/// doesn't name a known language.
+pub(crate) fn code_block(info: &str, code: &str, highlight: Highlight) -> Option<String> {
+    let language = info
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .next()
+        .unwrap_or_default();
+    let syntax = match syntaxes().find_syntax_by_token(language) {
+        Some(syntax) if !language.is_empty() => syntax,
+        _ => return None,
+    };
+
+    highlight.render(code, syntax, language).or_else(|| {
+        let mut html = String::with_capacity(code.len() + 64);
+        let _ = html.write_unescaped("<pre><code class=\"language-");
+        let _ = html.write_escaped(language);
+        let _ = html.write_unescaped("\">");
+        let _ = html.write_escaped(code);
+        let _ = html.write_unescaped("</code></pre>\n");
+
+        Some(html)
+    })
+}
@@ -0,0 +1,193 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::hash::BuildHasher;
+
+use beef::Cow;
+
+use crate::encoding::Encoder;
+#[cfg(feature = "indexes")]
+use crate::template::Indexed;
+use crate::traits::ContentSequence;
+use crate::{normalize, Content, Error, Ramhorns, Section, Template};
+
+/// Name of the field under which a layout receives the rendered page.
+const SLOT: &str = "content";
+
+impl<H: BuildHasher + Default> Ramhorns<H> {
+    /// Render the template `name` and place it inside the template `layout`,
+    /// where it is available as `{{{content}}}`. All other fields of `content`
+    /// remain accessible to the layout, eg: for titles and menus.
+    /// ```no_run
+    /// # use ramhorns::{Content, Ramhorns};
+    /// #[derive(Content)]
+    /// struct Page<'a> {
+    ///     title: &'a str,
+    /// }
+    ///
+    /// let tpls: Ramhorns = Ramhorns::from_folder("./templates").unwrap();
+    /// let rendered = tpls.render_in_layout("page.html", "layout.html", &Page { title: "Hello" });
+    /// ```
+    pub fn render_in_layout<C: Content>(
+        &self,
+        name: &str,
+        layout: &str,
+        content: &C,
+    ) -> Result<String, Error> {
+        let page = self.get(name).ok_or_else(|| Error::NotFound(name.into()))?;
+        let layout = self
+            .get(layout)
+            .ok_or_else(|| Error::NotFound(layout.into()))?;
+
+        Ok(layout.render(&Layout {
+            body: &page.render(content),
+            content,
+        }))
+    }
+
+    /// Set the default layout for all templates inside the folder `dir`, including
+    /// its subfolders, unless they have a layout of their own. Use `""` for the
+    /// whole templates folder.
+    pub fn set_layout<L>(&mut self, dir: &str, layout: L)
+    where
+        L: Into<Cow<'static, str>>,
+    {
+        let dir = normalize(dir).trim_end_matches('/').to_string();
+
+        self.layouts.insert(Cow::owned(dir), layout.into());
+    }
+
+    /// Get the name of the default layout for the template with the given name.
+    pub fn layout_for(&self, name: &str) -> Option<&str> {
+        let name = normalize(name);
+        let mut dir = name.as_ref();
+
+        loop {
+            dir = match dir.rfind('/') {
+                Some(idx) => &dir[..idx],
+                None if dir.is_empty() => return None,
+                None => "",
+            };
+
+            if let Some(layout) = self.layouts.get(dir) {
+                return Some(layout);
+            }
+        }
+    }
+
+    /// Render the template `name`, placing it inside its default layout, if
+    /// one has been set for its folder with [`set_layout`](#method.set_layout).
+    pub fn render<C: Content>(&self, name: &str, content: &C) -> Result<String, Error> {
+        match self.layout_for(name) {
+            Some(layout) if layout != name => self.render_in_layout(name, layout, content),
+            _ => self
+                .get(name)
+                .map(|tpl| tpl.render(content))
+                .ok_or_else(|| Error::NotFound(name.into())),
+        }
+    }
+}
+
+/// `Content` of a layout, exposing the rendered page on top of the page's `Content`.
+struct Layout<'a, C> {
+    body: &'a str,
+    content: &'a C,
+}
+
+impl<C: Content> Content for Layout<'_, C> {
+    #[inline]
+    fn capacity_hint(&self, tpl: &Template) -> usize {
+        self.body.len() + self.content.capacity_hint(tpl)
+    }
+
+    #[inline]
+    fn render_section<P, E>(&self, section: Section<P>, encoder: &mut E) -> Result<(), E::Error>
+    where
+        P: ContentSequence,
+        E: Encoder,
+    {
+        section.with(self).render(encoder)
+    }
+
+    #[inline]
+    fn render_field_escaped<E: Encoder>(
+        &self,
+        hash: u64,
+        name: &str,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error> {
+        match name {
+            SLOT => self.body.render_escaped(encoder).map(|_| true),
+            _ => self.content.render_field_escaped(hash, name, encoder),
+        }
+    }
+
+    #[inline]
+    fn render_field_unescaped<E: Encoder>(
+        &self,
+        hash: u64,
+        name: &str,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error> {
+        match name {
+            SLOT => self.body.render_unescaped(encoder).map(|_| true),
+            _ => self.content.render_field_unescaped(hash, name, encoder),
+        }
+    }
+
+    #[inline]
+    fn render_field_section<P, E>(
+        &self,
+        hash: u64,
+        name: &str,
+        section: Section<P>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        P: ContentSequence,
+        E: Encoder,
+    {
+        match name {
+            SLOT => self.body.render_section(section, encoder).map(|_| true),
+            _ => self
+                .content
+                .render_field_section(hash, name, section, encoder),
+        }
+    }
+
+    #[inline]
+    fn render_field_inverse<P, E>(
+        &self,
+        hash: u64,
+        name: &str,
+        section: Section<P>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        P: ContentSequence,
+        E: Encoder,
+    {
+        match name {
+            SLOT => self.body.render_inverse(section, encoder).map(|_| true),
+            _ => self
+                .content
+                .render_field_inverse(hash, name, section, encoder),
+        }
+    }
+
+    #[cfg(feature = "indexes")]
+    #[inline]
+    fn render_index_section<'section, P, E>(
+        &self,
+        indexed: &Indexed,
+        section: Section<'section, P>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        P: ContentSequence,
+        E: Encoder,
+    {
+        self.content.render_index_section(indexed, section, encoder)
+    }
+}
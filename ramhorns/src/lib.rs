@@ -70,24 +70,56 @@
 //! ```
 
 #![warn(missing_docs)]
+use std::borrow::Cow as StdCow;
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::BuildHasher;
-use std::path::{Path, PathBuf};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::Arc;
 
 use beef::Cow;
 use std::io::ErrorKind;
 
+#[macro_use]
 mod content;
 mod error;
+mod escape;
+mod folder;
+#[cfg(feature = "syntect")]
+mod highlight;
+mod layout;
+mod locale;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod output;
+mod search;
+#[cfg(feature = "serde")]
+mod serialize;
+mod shared;
 mod template;
 pub mod traits;
+#[cfg(any(feature = "serde_json", feature = "serde_norway", feature = "toml"))]
+mod value;
 
 pub mod encoding;
 
 pub use content::Content;
 pub use error::Error;
-pub use template::{Section, Template};
+pub use escape::{Escape, Escaper, Escaping};
+pub use folder::{FolderOptions, Symlinks};
+#[cfg(feature = "syntect")]
+pub use highlight::Highlight;
+#[cfg(feature = "comrak")]
+pub use markdown::Comrak;
+#[cfg(feature = "markdown")]
+pub use markdown::{DefaultBackend, MarkdownBackend, MarkdownOptions};
+#[cfg(feature = "pulldown-cmark")]
+pub use markdown::{Heading, MarkdownDocument, PulldownCmark};
+pub use output::{OutputFile, OutputOptions, OutputStatus};
+#[cfg(feature = "serde")]
+pub use serialize::Serde;
+pub use shared::SharedRamhorns;
+pub use template::{Bound, Section, Template};
 
 #[cfg(feature = "indexes")]
 pub use template::{Index, Indexed};
@@ -98,10 +130,29 @@ pub use ramhorns_derive::Content;
 /// Aggregator for [`Template`s](./struct.Template.html), that allows them to
 /// be loaded from the file system and use partials: `{{>partial}}`
 ///
+/// Templates are named by their path relative to the templates folder, using `/`
+/// as separator on every platform, eg: `blog/post.html`.
+///
 /// For faster or DOS-resistant hashes, it is recommended to use
 /// [aHash](https://docs.rs/ahash/latest/ahash/) `RandomState` as hasher.
 pub struct Ramhorns<H = fnv::FnvBuildHasher> {
-    partials: HashMap<Cow<'static, str>, Template<'static>, H>,
+    partials: HashMap<Cow<'static, str>, Arc<Template<'static>>, H>,
+    /// Names without extension pointing to the names of the templates.
+    aliases: HashMap<Cow<'static, str>, Cow<'static, str>, H>,
+    extensionless: Option<Box<str>>,
+    /// Escaping strategies for templates with the given extensions.
+    escapes: Vec<(Box<str>, Escape)>,
+    /// Default layouts for templates inside the given folders.
+    layouts: HashMap<Cow<'static, str>, Cow<'static, str>, H>,
+    /// Locale of the template currently being parsed, used to resolve its partials.
+    locale: Option<Box<str>>,
+    /// Locales recognized in the names of template variants.
+    locales: Vec<Box<str>>,
+    /// Partials parsed again for the locale of their includer, by locale and name.
+    inherited: HashMap<String, Arc<Template<'static>>, H>,
+    /// Markdown extensions for `{{#md}}` sections of all templates, if enabled.
+    #[cfg(feature = "markdown")]
+    markdown: Option<MarkdownOptions>,
     dir: PathBuf,
 }
 
@@ -138,13 +189,55 @@ impl<H: BuildHasher + Default> Ramhorns<H> {
     pub fn from_folder_with_extension<P: AsRef<Path>>(
         dir: P,
         extension: &str,
+    ) -> Result<Self, Error> {
+        Self::from_folder_with_options(dir, &FolderOptions::new().extension(extension))
+    }
+
+    /// Loads all files matching the given [`FolderOptions`](./struct.FolderOptions.html)
+    /// as templates from the given folder, making them accessible via their path,
+    /// joining partials as required.
+    /// ```no_run
+    /// # use ramhorns::{FolderOptions, Ramhorns};
+    /// let options = FolderOptions::new().extension("html").exclude("drafts/**");
+    /// let tpls: Ramhorns = Ramhorns::from_folder_with_options("./templates", &options).unwrap();
+    /// let content = "I am the content";
+    /// let rendered = tpls.get("hello.html").unwrap().render(&content);
+    /// ```
+    pub fn from_folder_with_options<P: AsRef<Path>>(
+        dir: P,
+        options: &FolderOptions,
     ) -> Result<Self, Error> {
         let mut templates = Ramhorns::lazy(dir)?;
-        templates.load_folder(&templates.dir.clone(), extension)?;
+        templates.load_folder(&templates.dir.clone(), options)?;
 
         Ok(templates)
     }
 
+    /// Loads all files matching the given [`FolderOptions`](./struct.FolderOptions.html)
+    /// like [`from_folder_with_options`](#method.from_folder_with_options), but keeps
+    /// loading other files when a template fails to load. Returns the templates that
+    /// loaded successfully, along with the errors of those that didn't.
+    /// ```no_run
+    /// # use ramhorns::{FolderOptions, Ramhorns};
+    /// let (tpls, errors): (Ramhorns, _) =
+    ///     Ramhorns::from_folder_with_errors("./templates", &FolderOptions::new()).unwrap();
+    ///
+    /// for error in errors {
+    ///     eprintln!("{}", error);
+    /// }
+    /// let rendered = tpls.get("hello.html").unwrap().render(&"I am the content");
+    /// ```
+    pub fn from_folder_with_errors<P: AsRef<Path>>(
+        dir: P,
+        options: &FolderOptions,
+    ) -> Result<(Self, Vec<Error>), Error> {
+        let mut templates = Ramhorns::lazy(dir)?;
+        let options = options.clone().collect_errors(true);
+        let errors = templates.collect_folder(&templates.dir.clone(), &options)?;
+
+        Ok((templates, errors))
+    }
+
     /// Extends the template collection with files with `.html` extension
     /// from the given folder, making them accessible via their path, joining partials as
     /// required.
@@ -164,30 +257,25 @@ impl<H: BuildHasher + Default> Ramhorns<H> {
         &mut self,
         dir: P,
         extension: &str,
+    ) -> Result<(), Error> {
+        self.extend_from_folder_with_options(dir, &FolderOptions::new().extension(extension))
+    }
+
+    /// Extends the template collection with files matching the given
+    /// [`FolderOptions`](./struct.FolderOptions.html) from the given folder, making them
+    /// accessible via their path, joining partials as required.
+    /// If there is a file with the same name as a  previously loaded template or partial,
+    /// it will not be loaded.
+    pub fn extend_from_folder_with_options<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        options: &FolderOptions,
     ) -> Result<(), Error> {
         let dir = std::mem::replace(&mut self.dir, dir.as_ref().canonicalize()?);
-        self.load_folder(&self.dir.clone(), extension)?;
+        let result = self.load_folder(&self.dir.clone(), options);
         self.dir = dir;
 
-        Ok(())
-    }
-
-    fn load_folder(&mut self, dir: &Path, extension: &str) -> Result<(), Error> {
-        for entry in std::fs::read_dir(dir)? {
-            let path = entry?.path();
-            if path.is_dir() {
-                self.load_folder(&path, extension)?;
-            } else if path.extension().map(|e| e == extension).unwrap_or(false) {
-                let name = path
-                    .strip_prefix(&self.dir)
-                    .unwrap_or(&path)
-                    .to_string_lossy();
-                if !self.partials.contains_key(name.as_ref()) {
-                    self.load_internal(&path, Cow::owned(name.to_string()))?;
-                }
-            }
-        }
-        Ok(())
+        result
     }
 
     /// Create a new empty aggregator for a given folder. This won't do anything until
@@ -201,13 +289,113 @@ impl<H: BuildHasher + Default> Ramhorns<H> {
     pub fn lazy<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
         Ok(Ramhorns {
             partials: HashMap::default(),
+            aliases: HashMap::default(),
+            extensionless: None,
+            escapes: Vec::new(),
+            layouts: HashMap::default(),
+            locale: None,
+            locales: Vec::new(),
+            inherited: HashMap::default(),
+            #[cfg(feature = "markdown")]
+            markdown: None,
             dir: dir.as_ref().canonicalize()?,
         })
     }
 
+    /// Make templates with the given extension accessible by their name without
+    /// it as well, so that `blog/post.html` can be used as `{{>blog/post}}`.
+    /// Names without any extension that are loaded from files will have it
+    /// appended. This should be set before any templates are loaded.
+    /// ```no_run
+    /// # use ramhorns::Ramhorns;
+    /// let mut tpls: Ramhorns = Ramhorns::lazy("./templates").unwrap().extensionless("html");
+    /// tpls.extend_from_folder("./templates").unwrap();
+    ///
+    /// let content = "I am the content";
+    /// let rendered = tpls.get("hello").unwrap().render(&content);
+    /// assert_eq!(rendered, tpls.get("hello.html").unwrap().render(&content));
+    /// ```
+    pub fn extensionless(mut self, extension: &str) -> Self {
+        self.extensionless = Some(extension.into());
+        self
+    }
+
+    /// Escape values in templates with the given extension using the `escape`
+    /// strategy instead of HTML escaping. This should be set before any templates
+    /// are loaded.
+    /// ```no_run
+    /// # use ramhorns::{Escape, Ramhorns};
+    /// let mut tpls: Ramhorns = Ramhorns::lazy("./templates")
+    ///     .unwrap()
+    ///     .escape_extension("tex", Escape::Latex)
+    ///     .escape_extension("txt", Escape::None);
+    ///
+    /// let rendered = tpls.from_file("letter.tex").unwrap().render(&"50% off");
+    /// ```
+    pub fn escape_extension(mut self, extension: &str, escape: Escape) -> Self {
+        self.escapes.retain(|(e, _)| &**e != extension);
+        self.escapes.push((extension.into(), escape));
+        self
+    }
+
+    /// Render `{{#md}}` sections of all templates as Markdown with the given
+    /// extensions, see [`Template::with_markdown`](./struct.Template.html#method.with_markdown).
+    /// This should be set before any templates are loaded.
+    /// ```no_run
+    /// # use ramhorns::{MarkdownOptions, Ramhorns};
+    /// let tpls: Ramhorns = Ramhorns::lazy("./templates")
+    ///     .unwrap()
+    ///     .markdown(MarkdownOptions::new().tables().footnotes());
+    /// ```
+    #[cfg(feature = "markdown")]
+    pub fn markdown(mut self, markdown: MarkdownOptions) -> Self {
+        self.markdown = Some(markdown);
+        self
+    }
+
     /// Get the template with the given name, if it exists.
     pub fn get(&self, name: &str) -> Option<&Template<'static>> {
-        self.partials.get(name)
+        self.lookup(name).map(|tpl| &**tpl)
+    }
+
+    /// Get a shared handle to the template with the given name, if it exists.
+    /// The handle stays valid even if the template is later replaced or removed.
+    pub fn get_shared(&self, name: &str) -> Option<Arc<Template<'static>>> {
+        self.lookup(name).cloned()
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Arc<Template<'static>>> {
+        let name = normalize(name);
+
+        match self.partials.get(name.as_ref()) {
+            Some(tpl) => Some(tpl),
+            None => self.partials.get(self.aliases.get(name.as_ref())?.as_ref()),
+        }
+    }
+
+    /// Name of the file to load the template with the given name from.
+    fn file_name<'a>(&self, name: &'a str) -> StdCow<'a, str> {
+        let name = normalize(name);
+
+        match &self.extensionless {
+            Some(extension) if Path::new(name.as_ref()).extension().is_none() => {
+                StdCow::Owned(format!("{}.{}", name, extension))
+            }
+            _ => name,
+        }
+    }
+
+    /// Name without extension under which the template with the given name is
+    /// also accessible, if any.
+    fn alias(&self, name: &str) -> Option<String> {
+        let extension = self.extensionless.as_deref()?;
+        let alias = name.strip_suffix(extension)?.strip_suffix('.')?;
+
+        if alias.is_empty() || alias.ends_with('/') {
+            return None;
+        }
+
+        Some(alias.to_string())
     }
 
     /// Get the template with the given name. If the template doesn't exist,
@@ -215,24 +403,65 @@ impl<H: BuildHasher + Default> Ramhorns<H> {
     ///
     /// Use this method in tandem with [`lazy`](#method.lazy).
     pub fn from_file(&mut self, name: &str) -> Result<&Template<'static>, Error> {
-        let path = self.dir.join(name);
-        if !self.partials.contains_key(name) {
-            self.load_internal(&path, Cow::owned(name.to_string()))?;
+        self.load(name).map(|tpl| &**tpl)
+    }
+
+    pub(crate) fn load(&mut self, name: &str) -> Result<&Arc<Template<'static>>, Error> {
+        if self.lookup(name).is_none() {
+            let file = self.file_name(name).into_owned();
+
+            if !self.partials.contains_key(file.as_str()) {
+                let path = self.dir.join(&file);
+                self.load_internal(&path, Cow::owned(file))?;
+            }
         }
-        Ok(&self.partials[name])
+        self.lookup(name)
+            .ok_or_else(|| Error::NotFound(name.into()))
     }
 
     // Unsafe to expose as it loads the template from arbitrary path.
     #[inline]
     fn load_internal(&mut self, path: &Path, name: Cow<'static, str>) -> Result<(), Error> {
-        let file = match std::fs::read_to_string(path) {
-            Ok(file) => Ok(file),
-            Err(e) if e.kind() == ErrorKind::NotFound => {
-                Err(Error::NotFound(name.to_string().into()))
-            }
-            Err(e) => Err(Error::Io(e)),
-        }?;
-        self.insert(file, name)
+        let file = read_file(path, &name)?;
+        let template = self
+            .parse(file, &name)
+            .map_err(|err| err.in_template(&name))?;
+        self.register(name, Arc::new(template));
+        Ok(())
+    }
+
+    /// Parse a template, resolving its partials with the locale of its name
+    /// and escaping values as set for its extension.
+    fn parse<S>(&mut self, src: S, name: &str) -> Result<Template<'static>, Error>
+    where
+        S: Into<Cow<'static, str>>,
+    {
+        // Partials without a locale of their own keep the one of their includer
+        let locale = match locale::locale_of(name, &self.locales) {
+            Some(locale) => Some(locale.into()),
+            None => self.locale.clone(),
+        };
+        let locale = std::mem::replace(&mut self.locale, locale);
+        let template = Template::load(src, self);
+        self.locale = locale;
+
+        #[cfg(feature = "markdown")]
+        let template = match self.markdown {
+            Some(markdown) => template.map(|tpl| tpl.with_markdown(markdown)),
+            None => template,
+        };
+
+        let extension = Path::new(name).extension();
+        let escape = self
+            .escapes
+            .iter()
+            .find(|(e, _)| extension == Some(e.as_ref().as_ref()))
+            .map(|(_, escape)| *escape);
+
+        match escape {
+            Some(escape) => template.map(|tpl| tpl.with_escape(escape)),
+            None => template,
+        }
     }
 
     /// Insert a template parsed from `src` with the name `name`.
@@ -245,25 +474,197 @@ impl<H: BuildHasher + Default> Ramhorns<H> {
         S: Into<Cow<'static, str>>,
         T: Into<Cow<'static, str>>,
     {
-        let template = Template::load(src, self)?;
-        self.partials.insert(name.into(), template);
+        self.replace(src, name).map(|_| ())
+    }
+
+    /// Insert a template parsed from `src` with the name `name`, returning the
+    /// template previously stored under this name, if any.
+    ///
+    /// # Warning
+    /// This can load partials from an arbitrary path. Use only with trusted source.
+    pub fn replace<S, T>(
+        &mut self,
+        src: S,
+        name: T,
+    ) -> Result<Option<Arc<Template<'static>>>, Error>
+    where
+        S: Into<Cow<'static, str>>,
+        T: Into<Cow<'static, str>>,
+    {
+        let name = normalize_owned(name.into());
+        let template = self.parse(src, &name)?;
+        Ok(self.register(name, Arc::new(template)))
+    }
+
+    fn register(
+        &mut self,
+        name: Cow<'static, str>,
+        template: Arc<Template<'static>>,
+    ) -> Option<Arc<Template<'static>>> {
+        if let Some(alias) = self.alias(&name) {
+            self.aliases.insert(Cow::owned(alias), name.clone());
+        }
+        self.inherited.clear();
+        self.partials.insert(name, template)
+    }
+
+    fn unregister(&mut self, name: &str) -> Option<Arc<Template<'static>>> {
+        let name = normalize(name);
+        let name = match self.partials.contains_key(name.as_ref()) {
+            true => name.into_owned(),
+            false => self.aliases.get(name.as_ref())?.to_string(),
+        };
+
+        if let Some(alias) = self.alias(&name) {
+            if self.aliases.get(alias.as_str()).map(|n| n.as_ref()) == Some(name.as_str()) {
+                self.aliases.remove(alias.as_str());
+            }
+        }
+        self.inherited.clear();
+        self.partials.remove(name.as_str())
+    }
+
+    /// Remove the template with the given name, returning it if it was present.
+    ///
+    /// Templates that included the removed one as a partial are not affected.
+    pub fn remove(&mut self, name: &str) -> Option<Arc<Template<'static>>> {
+        self.unregister(name)
+    }
+
+    /// Check if a template with the given name is present.
+    pub fn contains(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+
+    /// Number of templates in this collection.
+    pub fn len(&self) -> usize {
+        self.partials.len()
+    }
+
+    /// Check if this collection has no templates.
+    pub fn is_empty(&self) -> bool {
+        self.partials.is_empty()
+    }
+
+    /// Iterate over the names of all templates, in arbitrary order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.partials.keys().map(|name| name.as_ref())
+    }
+
+    /// Iterate over all templates and their names, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Template<'static>)> + '_ {
+        self.partials
+            .iter()
+            .map(|(name, tpl)| (name.as_ref(), &**tpl))
+    }
+
+    /// Retain only the templates for which the predicate returns `true`.
+    ///
+    /// Templates that included a removed one as a partial are not affected.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &Template<'static>) -> bool,
+    {
+        self.partials.retain(|name, tpl| f(name, tpl));
+
+        let partials = &self.partials;
+        self.aliases
+            .retain(|_, name| partials.contains_key(name.as_ref()));
+    }
+
+    /// Move the template stored under `from` to the name `to`, replacing any
+    /// template already stored under `to`.
+    ///
+    /// Partial tags are resolved when a template is parsed, so templates that
+    /// already included `from` are not affected, while templates parsed
+    /// afterwards have to include it as `to`.
+    pub fn rename<T>(&mut self, from: &str, to: T) -> Result<(), Error>
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        let template = self
+            .unregister(from)
+            .ok_or_else(|| Error::NotFound(from.into()))?;
+        self.register(normalize_owned(to.into()), template);
         Ok(())
     }
 }
 
+/// Normalize a template name, so that paths using the platform separator or
+/// starting with `./` refer to the same template as plain paths using `/`.
+pub(crate) fn normalize(name: &str) -> StdCow<'_, str> {
+    if MAIN_SEPARATOR != '/' && name.contains(MAIN_SEPARATOR) {
+        StdCow::Owned(trim_current_dir(&name.replace(MAIN_SEPARATOR, "/")).to_string())
+    } else {
+        StdCow::Borrowed(trim_current_dir(name))
+    }
+}
+
+/// Normalize a template name to store a template under.
+fn normalize_owned(name: Cow<'static, str>) -> Cow<'static, str> {
+    match normalize(&name) {
+        StdCow::Borrowed(normalized) if normalized.len() == name.len() => name,
+        normalized => Cow::owned(normalized.into_owned()),
+    }
+}
+
+fn trim_current_dir(mut name: &str) -> &str {
+    while let Some(rest) = name.strip_prefix("./") {
+        name = rest;
+    }
+    name
+}
+
+/// Name of a template loaded from the given path relative to the templates folder.
+pub(crate) fn template_name(path: &Path) -> String {
+    let mut name = String::new();
+
+    for component in path.components() {
+        if !name.is_empty() {
+            name.push('/');
+        }
+        name.push_str(&component.as_os_str().to_string_lossy());
+    }
+    name
+}
+
+/// Read the source of a template, reporting a missing file as `Error::NotFound`.
+pub(crate) fn read_file(path: &Path, name: &str) -> Result<String, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(file) => Ok(file),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(Error::NotFound(name.into())),
+        Err(e) => Err(Error::Io(e)),
+    }
+}
+
 pub(crate) trait Partials<'tpl> {
-    fn get_partial(&mut self, name: &'tpl str) -> Result<&Template<'tpl>, Error>;
+    fn get_partial(&mut self, name: &'tpl str) -> Result<Arc<Template<'tpl>>, Error>;
 }
 
 impl<H: BuildHasher + Default> Partials<'static> for Ramhorns<H> {
-    fn get_partial(&mut self, name: &'static str) -> Result<&Template<'static>, Error> {
-        if !self.partials.contains_key(name) {
-            let path = self.dir.join(name).canonicalize()?;
+    fn get_partial(&mut self, name: &'static str) -> Result<Arc<Template<'static>>, Error> {
+        if let Some(partial) = self.get_localized_partial(name)? {
+            return Ok(partial);
+        }
+        if let Some(partial) = self.get_inherited_partial(name)? {
+            return Ok(partial);
+        }
+        if let Some(partial) = self.lookup(name) {
+            return Ok(partial.clone());
+        }
+
+        let file = match self.file_name(name) {
+            StdCow::Borrowed(file) => Cow::borrowed(file),
+            StdCow::Owned(file) => Cow::owned(file),
+        };
+
+        if !self.partials.contains_key(file.as_ref()) {
+            let path = self.dir.join(file.as_ref()).canonicalize()?;
             if !path.starts_with(&self.dir) {
                 return Err(Error::IllegalPartial(name.into()));
             }
-            self.load_internal(&path, Cow::borrowed(name))?;
+            self.load_internal(&path, file.clone())?;
         }
-        Ok(&self.partials[name])
+        Ok(self.partials[file.as_ref()].clone())
     }
 }
@@ -0,0 +1,240 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use beef::Cow;
+
+use crate::{read_file, Error, Ramhorns, Template};
+
+impl<H: BuildHasher + Default> Ramhorns<H> {
+    /// Treat templates with any of the given [BCP 47](https://www.rfc-editor.org/info/bcp47)
+    /// locales in front of their extension as variants of that locale, eg:
+    /// `welcome.de.html` for `de`. Other dotted segments, such as `js` in
+    /// `app.js.html`, are left alone. This should be set before any templates are
+    /// loaded. Locales requested from [`from_file_localized`](#method.from_file_localized)
+    /// are added as well.
+    /// ```no_run
+    /// # use ramhorns::Ramhorns;
+    /// let mut tpls: Ramhorns = Ramhorns::lazy("./templates").unwrap().locales(["de", "fr"]);
+    /// tpls.extend_from_folder("./templates").unwrap();
+    /// ```
+    pub fn locales<I>(mut self, locales: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        for locale in locales {
+            self.add_locale(locale.as_ref());
+        }
+        self
+    }
+
+    fn add_locale(&mut self, locale: &str) {
+        if !locale.is_empty() && !self.locales.iter().any(|l| &**l == locale) {
+            self.locales.push(locale.into());
+        }
+    }
+
+    /// Get the variant of the template with the given name best matching the
+    /// [BCP 47](https://www.rfc-editor.org/info/bcp47) `locale`, if it exists.
+    ///
+    /// Variants carry the locale in front of the extension, so for the locale
+    /// `de-AT` the template `welcome.html` is looked up as `welcome.de-AT.html`,
+    /// then `welcome.de.html`, and finally `welcome.html`.
+    ///
+    /// Partials included by a variant of a locale set with [`locales`](#method.locales)
+    /// are resolved in the same manner, preferring the locale of the variant, eg:
+    /// `{{>header.html}}` inside `welcome.de.html` includes `header.de.html` if such
+    /// file exists. This carries over to the partials of partials, even if those in
+    /// between have no variant.
+    /// ```no_run
+    /// # use ramhorns::Ramhorns;
+    /// let mut tpls: Ramhorns = Ramhorns::lazy("./templates").unwrap().locales(["de", "de-AT"]);
+    /// tpls.extend_from_folder("./templates").unwrap();
+    ///
+    /// let rendered = tpls.get_localized("welcome.html", "de-AT").unwrap().render(&"Servus!");
+    /// ```
+    pub fn get_localized(&self, name: &str, locale: &str) -> Option<&Template<'static>> {
+        let file = self.file_name(name);
+
+        fallbacks(locale)
+            .find_map(|locale| self.get(&localized(&file, locale)))
+            .or_else(|| self.get(name))
+    }
+
+    /// Get the variant of the template with the given name best matching the
+    /// `locale`, same as [`get_localized`](#method.get_localized). Variants that
+    /// haven't been loaded yet are loaded from file and parsed first.
+    ///
+    /// Use this method in tandem with [`lazy`](#method.lazy).
+    pub fn from_file_localized(
+        &mut self,
+        name: &str,
+        locale: &str,
+    ) -> Result<&Template<'static>, Error> {
+        let file = self.file_name(name).into_owned();
+
+        match self.load_localized(&file, locale)? {
+            Some(variant) => Ok(&self.partials[variant.as_str()]),
+            None => self.from_file(name),
+        }
+    }
+
+    /// Make sure the best matching variant of the template stored in the file `file`
+    /// is loaded, returning its name. Returns `None` if no variant exists.
+    fn load_localized(&mut self, file: &str, locale: &str) -> Result<Option<String>, Error> {
+        for locale in fallbacks(locale) {
+            self.add_locale(locale);
+        }
+        for locale in fallbacks(locale) {
+            let variant = localized(file, locale);
+
+            if self.partials.contains_key(variant.as_str()) {
+                return Ok(Some(variant));
+            }
+
+            let path = self.dir.join(&variant);
+
+            // Skip variants that don't exist, or are outside of the templates folder
+            match path.canonicalize() {
+                Ok(path) if path.starts_with(&self.dir) && path.is_file() => {}
+                _ => continue,
+            }
+
+            self.load_internal(&path, Cow::owned(variant.clone()))?;
+
+            return Ok(Some(variant));
+        }
+        Ok(None)
+    }
+
+    /// Get the variant of a partial matching the locale of the template currently
+    /// being parsed, if there is one.
+    pub(crate) fn get_localized_partial(
+        &mut self,
+        name: &str,
+    ) -> Result<Option<Arc<Template<'static>>>, Error> {
+        let locale = match self.locale.clone() {
+            Some(locale) => locale,
+            None => return Ok(None),
+        };
+        let file = self.file_name(name).into_owned();
+
+        Ok(self
+            .load_localized(&file, &locale)?
+            .map(|variant| self.partials[variant.as_str()].clone()))
+    }
+
+    /// Parse a partial without a variant for the locale of the template currently
+    /// being parsed, so that its own partials are resolved with that locale too.
+    /// The template registered under its name is left as it is, for templates in
+    /// other locales. Returns `None` if there is no such locale, template or file.
+    pub(crate) fn get_inherited_partial(
+        &mut self,
+        name: &str,
+    ) -> Result<Option<Arc<Template<'static>>>, Error> {
+        let locale = match &self.locale {
+            Some(locale) => locale,
+            None => return Ok(None),
+        };
+        let file = self.file_name(name).into_owned();
+        let key = format!("{}:{}", locale, file);
+
+        if let Some(partial) = self.inherited.get(&key) {
+            return Ok(Some(partial.clone()));
+        }
+        let src = match self.lookup(name) {
+            // Without partials of its own, the template is the same in every locale
+            Some(partial) if !partial.has_includes() => return Ok(Some(partial.clone())),
+            Some(partial) => partial.source().to_owned(),
+            None => match self.dir.join(&file).canonicalize() {
+                Ok(path) if path.starts_with(&self.dir) && path.is_file() => {
+                    read_file(&path, &file)?
+                }
+                _ => return Ok(None),
+            },
+        };
+        let template = Arc::new(
+            self.parse(src, &file)
+                .map_err(|err| err.in_template(&file))?,
+        );
+        self.inherited.insert(key, template.clone());
+
+        Ok(Some(template))
+    }
+}
+
+/// The locale of a template variant, if the name has one of the given `locales`,
+/// eg: `de-AT` for `welcome.de-AT.html`.
+pub(crate) fn locale_of<'a>(name: &'a str, locales: &[Box<str>]) -> Option<&'a str> {
+    let file = &name[name.rfind('/').map(|idx| idx + 1).unwrap_or(0)..];
+    let stem = &file[..file.rfind('.')?];
+    let locale = &stem[stem.rfind('.')? + 1..];
+
+    locales.iter().find(|l| ***l == *locale).map(|_| locale)
+}
+
+/// Locales to try for the given locale, from most to least specific,
+/// eg: `zh-Hant-TW`, `zh-Hant`, `zh`.
+fn fallbacks(locale: &str) -> impl Iterator<Item = &str> {
+    let mut next = Some(locale).filter(|locale| !locale.is_empty());
+
+    std::iter::from_fn(move || {
+        let locale = next?;
+        next = locale.rfind('-').map(|idx| &locale[..idx]);
+        Some(locale)
+    })
+}
+
+/// Name of the variant of a template for the given locale.
+fn localized(name: &str, locale: &str) -> String {
+    let file = name.rfind('/').map(|idx| idx + 1).unwrap_or(0);
+
+    match name[file..].rfind('.') {
+        Some(idx) => {
+            let (stem, extension) = name.split_at(file + idx);
+            format!("{}.{}{}", stem, locale, extension)
+        }
+        None => format!("{}.{}", name, locale),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn locale_fallbacks() {
+        assert_eq!(fallbacks("de-AT").collect::<Vec<_>>(), ["de-AT", "de"]);
+        assert_eq!(
+            fallbacks("zh-Hant-TW").collect::<Vec<_>>(),
+            ["zh-Hant-TW", "zh-Hant", "zh"]
+        );
+        assert_eq!(fallbacks("").count(), 0);
+    }
+
+    #[test]
+    fn localized_names() {
+        assert_eq!(localized("welcome.html", "de"), "welcome.de.html");
+        assert_eq!(localized("blog.d/post", "de-AT"), "blog.d/post.de-AT");
+
+        let locales: Vec<Box<str>> = vec!["de-AT".into(), "zh-Hant-TW".into(), "de".into()];
+        assert_eq!(
+            locale_of("blog/welcome.de-AT.html", &locales),
+            Some("de-AT")
+        );
+        assert_eq!(
+            locale_of("welcome.zh-Hant-TW.html", &locales),
+            Some("zh-Hant-TW")
+        );
+        assert_eq!(locale_of("welcome.html", &locales), None);
+        assert_eq!(locale_of("blog.de/welcome.html", &locales), None);
+        assert_eq!(locale_of("welcome.fr.html", &locales), None);
+        assert_eq!(locale_of("app.js.html", &locales), None);
+        assert_eq!(locale_of("index.md.html", &locales), None);
+        assert_eq!(locale_of("welcome.DE.html", &locales), None);
+    }
+}
@@ -0,0 +1,85 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "syntect")]
+use comrak::nodes::NodeHtmlBlock;
+use comrak::nodes::NodeValue;
+use comrak::{format_html, parse_document, Arena, Options};
+
+use super::{sanitize_html, MarkdownBackend, MarkdownOptions};
+use crate::encoding::{is_safe_url, INVALID_URL};
+#[cfg(feature = "syntect")]
+use crate::highlight;
+
+/// Renders Markdown with [`comrak`](https://docs.rs/comrak), a port of the
+/// GitHub Flavored Markdown reference implementation.
+///
+/// Unsafe tags such as `<script>` are always filtered, as on GitHub, while
+/// the extensions are set by [`MarkdownOptions`](./struct.MarkdownOptions.html).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Comrak;
+
+impl MarkdownOptions {
+    /// Options for `comrak`.
+    pub fn to_comrak(self) -> Options<'static> {
+        let mut options = Options::default();
+
+        options.extension.table = self.tables;
+        options.extension.footnotes = self.footnotes;
+        options.extension.strikethrough = self.strikethrough;
+        options.extension.tasklist = self.tasklists;
+        options.extension.autolink = self.autolinks;
+        options.extension.tagfilter = true;
+        options.parse.smart = self.smart_punctuation;
+        // Raw HTML is sanitized before rendering if need be.
+        options.render.unsafe_ = true;
+        options
+    }
+}
+
+impl MarkdownBackend for Comrak {
+    fn render(source: &str, options: MarkdownOptions, html: &mut String) {
+        let arena = Arena::new();
+        let comrak_options = options.to_comrak();
+        let root = parse_document(&arena, source, &comrak_options);
+
+        for node in root.descendants() {
+            let value = &mut node.data.borrow_mut().value;
+
+            match value {
+                NodeValue::HtmlBlock(block) if options.sanitize => {
+                    block.literal = sanitize_html(&block.literal);
+                }
+                NodeValue::HtmlInline(inline) if options.sanitize => {
+                    *inline = sanitize_html(inline);
+                }
+                NodeValue::Link(link) | NodeValue::Image(link)
+                    if options.sanitize && !is_safe_url(&link.url) =>
+                {
+                    link.url = INVALID_URL.into();
+                }
+                #[cfg(feature = "syntect")]
+                NodeValue::CodeBlock(block) if block.fenced => {
+                    let code = options
+                        .highlight
+                        .and_then(|hl| highlight::code_block(&block.info, &block.literal, hl));
+
+                    if let Some(literal) = code {
+                        *value = NodeValue::HtmlBlock(NodeHtmlBlock {
+                            block_type: 0,
+                            literal,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut out = Vec::with_capacity(source.len() * 3 / 2);
+
+        // Writing to a `Vec` can't fail, and the output is UTF-8 like the source.
+        let _ = format_html(root, &comrak_options, &mut out);
+        html.push_str(&String::from_utf8_lossy(&out));
+    }
+}
@@ -0,0 +1,306 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::HashSet;
+
+use pulldown_cmark::html::push_html;
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+use super::pulldown::Events;
+use super::MarkdownOptions;
+use crate::encoding::Encoder;
+use crate::traits::ContentSequence;
+use crate::{Content, Section, Template};
+
+/// Markdown rendered ahead of time, along with a table of contents of its headings.
+///
+/// Headings get `id`s made from their text, unless one is set with the
+/// `heading_attributes` extension, which no generated `id` takes. As `Content`, the document has the fields
+/// `html` with the rendered HTML, and `toc` with the list of
+/// [`Heading`s](./struct.Heading.html). Rendering the document itself as a
+/// variable writes its HTML.
+///
+/// Documents are always rendered with `pulldown-cmark`, and only available with
+/// the `pulldown-cmark` feature. That's the case even when the `comrak` feature
+/// makes [`Comrak`](./struct.Comrak.html) the [`DefaultBackend`](./type.DefaultBackend.html)
+/// for templates, so documents don't get its GitHub extensions or tag filtering.
+/// ```
+/// # use ramhorns::{Content, MarkdownDocument, Template};
+/// #[derive(Content)]
+/// struct Page {
+///     doc: MarkdownDocument,
+/// }
+///
+/// let tpl = Template::new(
+///     "{{#doc}}<ul>{{#toc}}<li><a href=\"#{{slug}}\">{{text}}</a></li>{{/toc}}</ul>{{{html}}}{{/doc}}",
+/// )
+/// .unwrap();
+/// let page = Page {
+///     doc: MarkdownDocument::new("# Fish & Chips\n\n## Fish\n\nYummy"),
+/// };
+///
+/// assert_eq!(
+///     tpl.render(&page),
+///     "<ul><li><a href=\"#fish--chips\">Fish &amp; Chips</a></li>\
+///      <li><a href=\"#fish\">Fish</a></li></ul>\
+///      <h1 id=\"fish--chips\">Fish &amp; Chips</h1>\n<h2 id=\"fish\">Fish</h2>\n<p>Yummy</p>\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownDocument {
+    html: String,
+    toc: Vec<Heading>,
+}
+
+/// Heading of a [`MarkdownDocument`](./struct.MarkdownDocument.html), with the
+/// fields `level`, `text` and `slug` as `Content`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Level of the heading, from 1 for `<h1>` to 6.
+    pub level: u8,
+    /// Plain text of the heading.
+    pub text: String,
+    /// The `id` of the heading.
+    pub slug: String,
+}
+
+impl MarkdownDocument {
+    /// Render plain CommonMark `source`.
+    pub fn new(source: &str) -> Self {
+        Self::with_options(source, MarkdownOptions::new())
+    }
+
+    /// Render `source` with the given extensions.
+    pub fn with_options(source: &str, options: MarkdownOptions) -> Self {
+        let mut events: Vec<Event> = Events::new(source, options).collect();
+        let mut toc = Vec::new();
+        let mut heading = None;
+
+        // Explicit ids take priority, generated slugs can't take them away
+        let mut slugs: HashSet<String> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Start(Tag::Heading { id: Some(id), .. }) => Some(id.to_string()),
+                _ => None,
+            })
+            .collect();
+        let mut explicit = HashSet::new();
+
+        for idx in 0..events.len() {
+            match &events[idx] {
+                Event::Start(Tag::Heading { .. }) => heading = Some((idx, String::new())),
+                Event::Text(part) | Event::Code(part) => {
+                    if let Some((_, text)) = &mut heading {
+                        text.push_str(part);
+                    }
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    let (start, text) = match heading.take() {
+                        Some(heading) => heading,
+                        None => continue,
+                    };
+                    if let Event::Start(Tag::Heading { level, id, .. }) = &mut events[start] {
+                        let slug = match id {
+                            Some(id) if explicit.insert(id.to_string()) => id.to_string(),
+                            Some(id) => unique_slug(id, &mut slugs),
+                            None => unique_slug(&text, &mut slugs),
+                        };
+                        *id = Some(slug.clone().into());
+
+                        toc.push(Heading {
+                            level: *level as u8,
+                            text,
+                            slug,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut html = String::with_capacity(source.len() * 3 / 2);
+        push_html(&mut html, events.into_iter());
+
+        MarkdownDocument { html, toc }
+    }
+
+    /// The rendered HTML.
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+
+    /// Headings of the document, in order.
+    pub fn toc(&self) -> &[Heading] {
+        &self.toc
+    }
+}
+
+/// Slug of a heading, lowercase alphanumerics with dashes for spaces, numbered
+/// if it's been used before in the same document. Headings without any of those
+/// get `section-1`, `section-2` and so on.
+fn unique_slug(text: &str, slugs: &mut HashSet<String>) -> String {
+    let mut slug = String::with_capacity(text.len());
+
+    for ch in text.trim().chars() {
+        if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+            slug.extend(ch.to_lowercase());
+        } else if ch.is_whitespace() {
+            slug.push('-');
+        }
+    }
+
+    let mut count = 0;
+
+    if slug.is_empty() {
+        slug.push_str("section");
+        count = 1;
+    }
+
+    let mut unique = match count {
+        0 => slug.clone(),
+        count => format!("{}-{}", slug, count),
+    };
+
+    while !slugs.insert(unique.clone()) {
+        count += 1;
+        unique = format!("{}-{}", slug, count);
+    }
+    unique
+}
+
+impl Content for MarkdownDocument {
+    #[inline]
+    fn is_truthy(&self) -> bool {
+        !self.html.is_empty()
+    }
+
+    #[inline]
+    fn capacity_hint(&self, _tpl: &Template) -> usize {
+        self.html.len()
+    }
+
+    #[inline]
+    fn render_escaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        encoder.write_html(&self.html)
+    }
+
+    /// Render a section with self.
+    #[inline]
+    fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        if self.is_truthy() {
+            section.with(self).render(encoder)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn render_field_escaped<E>(&self, _: u64, name: &str, encoder: &mut E) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        match name {
+            "html" => self.render_escaped(encoder).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    fn render_field_unescaped<E>(
+        &self,
+        _: u64,
+        name: &str,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        match name {
+            "html" => self.render_unescaped(encoder).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    fn render_field_section<C, E>(
+        &self,
+        _: u64,
+        name: &str,
+        section: Section<C>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        match name {
+            "toc" => self.toc.render_section(section, encoder).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    fn render_field_inverse<C, E>(
+        &self,
+        _: u64,
+        name: &str,
+        section: Section<C>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        match name {
+            "toc" => self.toc.render_inverse(section, encoder).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+}
+
+impl Content for Heading {
+    #[inline]
+    fn capacity_hint(&self, tpl: &Template) -> usize {
+        tpl.capacity_hint() + self.text.len() + self.slug.len()
+    }
+
+    /// Render a section with self.
+    #[inline]
+    fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        section.with(self).render(encoder)
+    }
+
+    fn render_field_escaped<E>(&self, _: u64, name: &str, encoder: &mut E) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        match name {
+            "level" => self.level.render_escaped(encoder).map(|_| true),
+            "text" => self.text.render_escaped(encoder).map(|_| true),
+            "slug" => self.slug.render_escaped(encoder).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    fn render_field_unescaped<E>(
+        &self,
+        _: u64,
+        name: &str,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        match name {
+            "level" => self.level.render_unescaped(encoder).map(|_| true),
+            "text" => self.text.render_unescaped(encoder).map(|_| true),
+            "slug" => self.slug.render_unescaped(encoder).map(|_| true),
+            _ => Ok(false),
+        }
+    }
+}
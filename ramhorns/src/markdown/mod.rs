@@ -0,0 +1,413 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+
+#[cfg(feature = "pulldown-cmark")]
+use pulldown_cmark::Options;
+
+use crate::encoding::{encode_cmark_with, Encoder, EscapeContext};
+#[cfg(feature = "syntect")]
+use crate::highlight::Highlight;
+use crate::traits::ContentSequence;
+use crate::{Escape, Escaping, Section};
+
+#[cfg(feature = "comrak")]
+mod comrak;
+#[cfg(feature = "pulldown-cmark")]
+mod document;
+#[cfg(feature = "pulldown-cmark")]
+mod pulldown;
+
+#[cfg(feature = "comrak")]
+pub use self::comrak::Comrak;
+#[cfg(feature = "pulldown-cmark")]
+pub use document::{Heading, MarkdownDocument};
+#[cfg(feature = "pulldown-cmark")]
+pub use pulldown::PulldownCmark;
+
+/// Backend rendering `#[ramhorns(md)]` fields and `{{#md}}` sections, picked
+/// by the enabled cargo features: [`Comrak`](./struct.Comrak.html) with the
+/// `comrak` feature, [`PulldownCmark`](./struct.PulldownCmark.html) otherwise.
+#[cfg(feature = "comrak")]
+pub type DefaultBackend = Comrak;
+
+/// Backend rendering `#[ramhorns(md)]` fields and `{{#md}}` sections, picked
+/// by the enabled cargo features: `Comrak` with the `comrak` feature,
+/// [`PulldownCmark`](./struct.PulldownCmark.html) otherwise.
+#[cfg(all(feature = "pulldown-cmark", not(feature = "comrak")))]
+pub type DefaultBackend = PulldownCmark;
+
+#[cfg(not(any(feature = "pulldown-cmark", feature = "comrak")))]
+compile_error!("The `markdown` feature requires either the `pulldown-cmark` or `comrak` feature");
+
+/// Renders Markdown to HTML, see
+/// [`encode_markdown`](./encoding/fn.encode_markdown.html).
+pub trait MarkdownBackend {
+    /// Render `source` with the given extensions, appending the HTML to `html`.
+    fn render(source: &str, options: MarkdownOptions, html: &mut String);
+}
+
+/// Markdown extensions to enable when rendering `#[ramhorns(md)]` fields and
+/// `{{#md}}` sections, on top of plain CommonMark.
+///
+/// Fields select their extensions in the derive attribute, eg:
+/// `#[ramhorns(md(tables, footnotes))]`, while `{{#md}}` sections use the ones
+/// set for their template with
+/// [`Template::with_markdown`](./struct.Template.html#method.with_markdown).
+/// ```
+/// # use ramhorns::{Content, MarkdownOptions, Template};
+/// #[derive(Content)]
+/// struct Post<'a> {
+///     #[ramhorns(md(strikethrough))]
+///     body: &'a str,
+///     author: &'a str,
+/// }
+///
+/// let tpl = Template::new("{{body}}{{#md}}~~by~~ *{{author}}*{{/md}}")
+///     .unwrap()
+///     .with_markdown(MarkdownOptions::new().strikethrough());
+/// let post = Post { body: "~~Fish~~ Chips", author: "Maciej" };
+///
+/// assert_eq!(
+///     tpl.render(&post),
+///     "<p><del>Fish</del> Chips</p>\n<p><del>by</del> <em>Maciej</em></p>\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MarkdownOptions {
+    tables: bool,
+    footnotes: bool,
+    strikethrough: bool,
+    tasklists: bool,
+    smart_punctuation: bool,
+    heading_attributes: bool,
+    autolinks: bool,
+    sanitize: bool,
+    #[cfg(feature = "syntect")]
+    highlight: Option<Highlight>,
+}
+
+impl MarkdownOptions {
+    /// Plain CommonMark, without any extensions.
+    pub const fn new() -> Self {
+        MarkdownOptions {
+            tables: false,
+            footnotes: false,
+            strikethrough: false,
+            tasklists: false,
+            smart_punctuation: false,
+            heading_attributes: false,
+            autolinks: false,
+            sanitize: false,
+            #[cfg(feature = "syntect")]
+            highlight: None,
+        }
+    }
+
+    /// Enable all the extensions listed here, without sanitizing.
+    pub const fn all() -> Self {
+        Self::new()
+            .tables()
+            .footnotes()
+            .strikethrough()
+            .tasklists()
+            .smart_punctuation()
+            .heading_attributes()
+            .autolinks()
+    }
+
+    /// Enable GitHub flavored tables.
+    pub const fn tables(mut self) -> Self {
+        self.tables = true;
+        self
+    }
+
+    /// Enable footnotes, `[^1]`.
+    pub const fn footnotes(mut self) -> Self {
+        self.footnotes = true;
+        self
+    }
+
+    /// Enable strikethrough, `~~deleted~~`.
+    pub const fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    /// Enable task lists, `- [x] done`.
+    pub const fn tasklists(mut self) -> Self {
+        self.tasklists = true;
+        self
+    }
+
+    /// Replace quotes, dashes and ellipses with their typographic forms.
+    pub const fn smart_punctuation(mut self) -> Self {
+        self.smart_punctuation = true;
+        self
+    }
+
+    /// Enable ids and classes on headings, `# Heading {#id .class}`.
+    /// Ignored by the `comrak` backend.
+    pub const fn heading_attributes(mut self) -> Self {
+        self.heading_attributes = true;
+        self
+    }
+
+    /// Turn bare URLs and emails into links, `www.example.com`.
+    /// Ignored by the `pulldown-cmark` backend.
+    pub const fn autolinks(mut self) -> Self {
+        self.autolinks = true;
+        self
+    }
+
+    /// Sanitize the rendered HTML for Markdown from untrusted sources. Raw HTML
+    /// is escaped, except for a few formatting tags without attributes, such as
+    /// `<b>` or `<br>`, and URLs of links and images with schemes other than
+    /// `http`, `https`, `mailto` and `tel` are replaced with `about:invalid`.
+    /// ```
+    /// # use ramhorns::{Content, Template};
+    /// #[derive(Content)]
+    /// struct Comment<'a> {
+    ///     #[ramhorns(md(sanitize))]
+    ///     body: &'a str,
+    /// }
+    ///
+    /// let tpl = Template::new("{{body}}").unwrap();
+    /// let comment = Comment {
+    ///     body: "<b>Hi</b> <img src=x onerror=alert(1)> [me](javascript:alert(1))",
+    /// };
+    ///
+    /// assert_eq!(
+    ///     tpl.render(&comment),
+    ///     "<p><b>Hi</b> &lt;img src=x onerror=alert(1)&gt; <a href=\"about:invalid\">me</a></p>\n"
+    /// );
+    /// ```
+    pub const fn sanitize(mut self) -> Self {
+        self.sanitize = true;
+        self
+    }
+
+    /// Highlight fenced code blocks with CSS classes, see
+    /// [`Highlight`](./enum.Highlight.html).
+    #[cfg(feature = "syntect")]
+    pub const fn highlight(self) -> Self {
+        self.highlight_with(Highlight::Classes)
+    }
+
+    /// Highlight fenced code blocks as given.
+    #[cfg(feature = "syntect")]
+    pub const fn highlight_with(mut self, highlight: Highlight) -> Self {
+        self.highlight = Some(highlight);
+        self
+    }
+
+    /// Options for the `pulldown_cmark` parser.
+    #[cfg(feature = "pulldown-cmark")]
+    pub fn to_cmark(self) -> Options {
+        let mut options = Options::empty();
+
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options.set(Options::ENABLE_SMART_PUNCTUATION, self.smart_punctuation);
+        options.set(Options::ENABLE_HEADING_ATTRIBUTES, self.heading_attributes);
+        options
+    }
+
+    /// Whether the rendered HTML is sanitized.
+    #[inline]
+    pub fn sanitizes(self) -> bool {
+        self.sanitize
+    }
+}
+
+#[cfg(feature = "pulldown-cmark")]
+impl From<Options> for MarkdownOptions {
+    fn from(options: Options) -> Self {
+        MarkdownOptions {
+            tables: options.contains(Options::ENABLE_TABLES),
+            footnotes: options.contains(Options::ENABLE_FOOTNOTES),
+            strikethrough: options.contains(Options::ENABLE_STRIKETHROUGH),
+            tasklists: options.contains(Options::ENABLE_TASKLISTS),
+            smart_punctuation: options.contains(Options::ENABLE_SMART_PUNCTUATION),
+            heading_attributes: options.contains(Options::ENABLE_HEADING_ATTRIBUTES),
+            ..Self::new()
+        }
+    }
+}
+
+/// Raw HTML tags kept in sanitized Markdown, as long as they have no attributes.
+const ALLOWED_TAGS: &[&str] = &[
+    "b", "i", "em", "strong", "s", "u", "del", "ins", "mark", "small", "sub", "sup", "code", "kbd",
+    "br", "hr",
+];
+
+/// Escape raw HTML, keeping only the allowed tags.
+fn sanitize_html(html: &str) -> String {
+    let mut sanitized = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        let _ = sanitized.write_escaped(&rest[..start]);
+        rest = &rest[start..];
+
+        match allowed_tag(rest) {
+            Some(len) => {
+                sanitized.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+            None => {
+                sanitized.push_str("&lt;");
+                rest = &rest[1..];
+            }
+        }
+    }
+    let _ = sanitized.write_escaped(rest);
+
+    sanitized
+}
+
+/// Length of the allowed tag, eg: `<b>`, `</b>` or `<br />`, at the start of `html`.
+fn allowed_tag(html: &str) -> Option<usize> {
+    let tag = &html[..html.find('>')? + 1];
+    let name = tag[1..tag.len() - 1]
+        .strip_prefix('/')
+        .unwrap_or(&tag[1..tag.len() - 1]);
+    let name = name.strip_suffix('/').unwrap_or(name).trim_end();
+
+    ALLOWED_TAGS
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(name))
+        .then_some(tag.len())
+}
+
+/// Render a `{{#md}}` section as Markdown with the given extensions, escaping
+/// values inside of it with `escape`.
+pub(crate) fn render_section<C, E>(
+    section: Section<C>,
+    options: MarkdownOptions,
+    escape: Escape,
+    encoder: &mut E,
+) -> Result<(), E::Error>
+where
+    C: ContentSequence,
+    E: Encoder,
+{
+    let mut error = None;
+    let mut visit = |visit: Visit| {
+        match visit {
+            Visit::Block => encoder.visit_block(),
+            Visit::Iteration => encoder.visit_iteration(),
+        }
+        .map_err(|err| error = Some(err))
+    };
+    let mut buffer = MarkdownBuffer {
+        buf: String::new(),
+        escape,
+        visit: &mut visit,
+    };
+    let result = section.render_blocks(&mut buffer);
+    let source = buffer.buf;
+
+    if let Some(err) = error {
+        return Err(err);
+    }
+    debug_assert!(result.is_ok());
+
+    encode_cmark_with(&source, encoder, options)
+}
+
+enum Visit {
+    Block,
+    Iteration,
+}
+
+/// Encoder collecting the output of a `{{#md}}` section to be rendered as
+/// Markdown, while passing the visits through to the encoder it's rendered to.
+///
+/// This type doesn't depend on the wrapped encoder, so that rendering nested
+/// `{{#md}}` sections doesn't instantiate an endless chain of types. Errors of
+/// visits are kept by the closure.
+struct MarkdownBuffer<'a> {
+    buf: String,
+    /// Strategy the encoder rendered to escapes values with.
+    escape: Escape,
+    visit: &'a mut dyn FnMut(Visit) -> Result<(), ()>,
+}
+
+// Writing to a `String` can't fail, so the results are ignored below.
+impl Encoder for MarkdownBuffer<'_> {
+    type Error = ();
+
+    #[inline]
+    fn write_unescaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        let _ = self.buf.write_unescaped(part);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_escaped(&mut self, part: &str) -> Result<(), Self::Error> {
+        let _ = Escaping::new(&mut self.buf, self.escape).write_escaped(part);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_escaped_in(&mut self, part: &str, context: EscapeContext) -> Result<(), Self::Error> {
+        let _ = Escaping::new(&mut self.buf, self.escape).write_escaped_in(part, context);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_html(&mut self, html: &str) -> Result<(), Self::Error> {
+        let _ = self.buf.write_html(html);
+        Ok(())
+    }
+
+    #[inline]
+    fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        let _ = self.buf.format_unescaped(display);
+        Ok(())
+    }
+
+    #[inline]
+    fn format_escaped<D: fmt::Display>(&mut self, display: D) -> Result<(), Self::Error> {
+        let _ = Escaping::new(&mut self.buf, self.escape).format_escaped(display);
+        Ok(())
+    }
+
+    #[inline]
+    fn visit_block(&mut self) -> Result<(), Self::Error> {
+        (self.visit)(Visit::Block)
+    }
+
+    #[inline]
+    fn visit_iteration(&mut self) -> Result<(), Self::Error> {
+        (self.visit)(Visit::Iteration)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sanitizes_raw_html() {
+        assert_eq!(
+            sanitize_html("<b>bold</b><br/><BR >"),
+            "<b>bold</b><br/><BR >"
+        );
+        assert_eq!(
+            sanitize_html("<b onclick=\"x\">a & b</b>"),
+            "&lt;b onclick=&quot;x&quot;&gt;a &amp; b</b>"
+        );
+        assert_eq!(
+            sanitize_html("<script>alert(1)</script>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(sanitize_html("<!-- <b> -->"), "&lt;!-- <b> --&gt;");
+        assert_eq!(sanitize_html("1 < 2 <"), "1 &lt; 2 &lt;");
+    }
+}
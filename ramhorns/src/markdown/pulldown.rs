@@ -0,0 +1,143 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#[cfg(feature = "syntect")]
+use std::collections::VecDeque;
+
+use pulldown_cmark::html::push_html;
+#[cfg(feature = "syntect")]
+use pulldown_cmark::{CodeBlockKind, TagEnd};
+use pulldown_cmark::{CowStr, Event, Parser, Tag};
+
+use super::{sanitize_html, MarkdownBackend, MarkdownOptions};
+use crate::encoding::{is_safe_url, INVALID_URL};
+#[cfg(feature = "syntect")]
+use crate::highlight::{self, Highlight};
+
+/// Renders Markdown with [`pulldown-cmark`](https://docs.rs/pulldown-cmark),
+/// a fast CommonMark parser with the most common GitHub extensions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PulldownCmark;
+
+impl MarkdownBackend for PulldownCmark {
+    fn render(source: &str, options: MarkdownOptions, html: &mut String) {
+        push_html(html, Events::new(source, options));
+    }
+}
+
+/// Events of `source` parsed with the given options, sanitized and highlighted
+/// as set.
+pub(crate) struct Events<'a> {
+    parser: Parser<'a>,
+    options: MarkdownOptions,
+    /// Events of a code block that couldn't be highlighted, to be passed on.
+    #[cfg(feature = "syntect")]
+    pending: VecDeque<Event<'a>>,
+}
+
+impl<'a> Events<'a> {
+    pub fn new(source: &'a str, options: MarkdownOptions) -> Self {
+        Events {
+            parser: Parser::new_ext(source, options.to_cmark()),
+            options,
+            #[cfg(feature = "syntect")]
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Highlight the code block started by `event`, if any, consuming the rest
+    /// of it from the parser.
+    #[cfg(feature = "syntect")]
+    fn code_block(&mut self, event: Event<'a>, highlight: Highlight) -> Event<'a> {
+        let info = match &event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => info,
+            _ => return event,
+        };
+        let mut code = String::new();
+
+        for next in &mut self.parser {
+            if let Event::Text(text) = &next {
+                code.push_str(text);
+            }
+            let end = next == Event::End(TagEnd::CodeBlock);
+
+            self.pending.push_back(next);
+
+            if end {
+                break;
+            }
+        }
+
+        match highlight::code_block(info, &code, highlight) {
+            Some(html) => {
+                self.pending.clear();
+
+                Event::Html(html.into())
+            }
+            None => event,
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        #[cfg(feature = "syntect")]
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+
+        let mut event = self.parser.next()?;
+
+        if self.options.sanitize {
+            event = sanitize(event);
+        }
+
+        #[cfg(feature = "syntect")]
+        if let Some(highlight) = self.options.highlight {
+            event = self.code_block(event, highlight);
+        }
+
+        Some(event)
+    }
+}
+
+/// Sanitize an event of Markdown from an untrusted source.
+fn sanitize(event: Event) -> Event {
+    match event {
+        Event::Html(html) => Event::Html(sanitize_html(&html).into()),
+        Event::InlineHtml(html) => Event::InlineHtml(sanitize_html(&html).into()),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    }
+}
+
+fn sanitize_url(url: CowStr) -> CowStr {
+    match is_safe_url(&url) {
+        true => url,
+        false => INVALID_URL.into(),
+    }
+}
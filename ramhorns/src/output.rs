@@ -0,0 +1,164 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::hash::BuildHasher;
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+
+use crate::folder::glob;
+use crate::{Content, Error, Ramhorns};
+
+/// Options controlling which templates are rendered by
+/// [`Ramhorns::render_to_dir`](./struct.Ramhorns.html#method.render_to_dir).
+///
+/// Glob patterns are matched against template names, same as for
+/// [`FolderOptions`](./struct.FolderOptions.html).
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    include: Vec<Box<str>>,
+    dry_run: bool,
+}
+
+impl OutputOptions {
+    /// Create new options. Unless patterns are added, all templates are rendered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only render templates with names matching the glob pattern, or any of the
+    /// patterns if used multiple times.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Render templates in memory only, reporting what would be written
+    /// without touching the output folder.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+/// What happened, or in a dry run would happen, to an output file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStatus {
+    /// The file didn't exist and has been created.
+    Created,
+    /// The file existed with different contents and has been overwritten.
+    Updated,
+    /// The file already had the rendered contents and has been left alone.
+    Unchanged,
+}
+
+/// A file rendered by [`Ramhorns::render_to_dir`](./struct.Ramhorns.html#method.render_to_dir).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFile {
+    /// Name of the rendered template.
+    pub name: String,
+    /// Path of the output file.
+    pub path: PathBuf,
+    /// What happened to the output file.
+    pub status: OutputStatus,
+}
+
+impl<H: BuildHasher + Default> Ramhorns<H> {
+    /// Render all templates matching the [`OutputOptions`](./struct.OutputOptions.html)
+    /// to files under the folder `dir`, mirroring their paths in the templates folder.
+    ///
+    /// The `content` closure provides the `Content` for each template by its name,
+    /// templates for which it returns `None`, eg: partials, are not rendered. Templates
+    /// are placed inside their default layouts, same as with [`render`](#method.render).
+    /// Files that already have the rendered contents are not written again.
+    ///
+    /// Fails without writing anything if a template name, eg: one given to
+    /// [`insert`](#method.insert), is an absolute path or contains `..`.
+    ///
+    /// Returns the rendered files, sorted by template name.
+    /// ```no_run
+    /// # use ramhorns::{OutputOptions, Ramhorns};
+    /// let tpls: Ramhorns = Ramhorns::from_folder("./templates").unwrap();
+    /// let options = OutputOptions::new().include("pages/**");
+    ///
+    /// let written = tpls
+    ///     .render_to_dir("./public", &options, |name| Some(name.to_string()))
+    ///     .unwrap();
+    /// ```
+    pub fn render_to_dir<P, C, F>(
+        &self,
+        dir: P,
+        options: &OutputOptions,
+        mut content: F,
+    ) -> Result<Vec<OutputFile>, Error>
+    where
+        P: AsRef<Path>,
+        C: Content,
+        F: FnMut(&str) -> Option<C>,
+    {
+        let dir = dir.as_ref();
+        let mut names: Vec<&str> = self
+            .names()
+            .filter(|name| {
+                options.include.is_empty()
+                    || options.include.iter().any(|pattern| glob(pattern, name))
+            })
+            .collect();
+
+        names.sort_unstable();
+
+        // Names of inserted templates could point anywhere, check them before
+        // writing anything.
+        if let Some(name) = names.iter().find(|name| !is_relative(name)) {
+            return Err(Error::Io(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Template {} can't be rendered outside of {}",
+                    name,
+                    dir.display()
+                ),
+            )));
+        }
+
+        let mut files = Vec::with_capacity(names.len());
+
+        for name in names {
+            let content = match content(name) {
+                Some(content) => content,
+                None => continue,
+            };
+            let rendered = self.render(name, &content)?;
+            let path = dir.join(name);
+
+            let status = match std::fs::read(&path) {
+                Ok(existing) if existing == rendered.as_bytes() => OutputStatus::Unchanged,
+                Ok(_) => OutputStatus::Updated,
+                Err(e) if e.kind() == ErrorKind::NotFound => OutputStatus::Created,
+                Err(e) => return Err(Error::Io(e)),
+            };
+
+            if status != OutputStatus::Unchanged && !options.dry_run {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                // Not `Template::render_to_file`, the output is already rendered
+                // in its layout for the comparison above
+                std::fs::write(&path, rendered)?;
+            }
+
+            files.push(OutputFile {
+                name: name.to_string(),
+                path,
+                status,
+            });
+        }
+        Ok(files)
+    }
+}
+
+/// Whether the template name is a path that stays inside the output folder.
+fn is_relative(name: &str) -> bool {
+    let mut components = Path::new(name).components().peekable();
+
+    components.peek().is_some() && components.all(|c| matches!(c, Component::Normal(_)))
+}
@@ -0,0 +1,165 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Search for the next byte that has to be escaped.
+
+/// Up to this many distinct bytes are searched for with SIMD, tables escaping
+/// more bytes fall back to the scalar loop.
+pub(crate) const MAX_NEEDLES: usize = 16;
+
+/// Bytes to search for, along with a lookup table of the same bytes.
+#[derive(Clone, Copy)]
+pub(crate) struct Needles {
+    bytes: [u8; MAX_NEEDLES],
+    /// Number of bytes in use, or `MAX_NEEDLES + 1` if there are too many.
+    len: u8,
+    lookup: [bool; 128],
+}
+
+impl Needles {
+    pub const fn new() -> Self {
+        Needles {
+            bytes: [0; MAX_NEEDLES],
+            len: 0,
+            lookup: [false; 128],
+        }
+    }
+
+    pub const fn with(mut self, byte: u8) -> Self {
+        if self.lookup[byte as usize] {
+            return self;
+        }
+        self.lookup[byte as usize] = true;
+
+        if (self.len as usize) < MAX_NEEDLES {
+            self.bytes[self.len as usize] = byte;
+        }
+        if (self.len as usize) <= MAX_NEEDLES {
+            self.len += 1;
+        }
+        self
+    }
+
+    pub const fn without(mut self, byte: u8) -> Self {
+        if !self.lookup[byte as usize] {
+            return self;
+        }
+        self.lookup[byte as usize] = false;
+
+        // Rebuild the list of bytes from the lookup table, which might
+        // bring it back under the limit.
+        let mut needles = Needles::new();
+        let mut byte = 0;
+
+        while byte < 128 {
+            if self.lookup[byte] {
+                needles = needles.with(byte as u8);
+            }
+            byte += 1;
+        }
+        needles
+    }
+
+    /// Find the index of the first needle in `haystack` at or after `from`.
+    #[inline]
+    pub fn find(&self, haystack: &[u8], from: usize) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if self.len as usize <= MAX_NEEDLES {
+                // SSE2 is part of the x86_64 baseline.
+                return unsafe { self.find_sse2(haystack, from) };
+            }
+        }
+
+        self.find_scalar(haystack, from)
+    }
+
+    #[inline]
+    fn find_scalar(&self, haystack: &[u8], from: usize) -> Option<usize> {
+        haystack
+            .get(from..)?
+            .iter()
+            .position(|&byte| byte < 128 && self.lookup[byte as usize])
+            .map(|idx| from + idx)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "sse2")]
+    unsafe fn find_sse2(&self, haystack: &[u8], from: usize) -> Option<usize> {
+        use std::arch::x86_64::*;
+
+        const CHUNK: usize = 16;
+
+        let needles = &self.bytes[..self.len as usize];
+        let mut idx = from;
+
+        if needles.is_empty() {
+            return None;
+        }
+
+        while idx + CHUNK <= haystack.len() {
+            // The loop condition keeps the 16 bytes loaded within `haystack`.
+            let chunk = _mm_loadu_si128(haystack.as_ptr().add(idx) as *const __m128i);
+            let mut found = _mm_setzero_si128();
+
+            for &needle in needles {
+                let needle = _mm_set1_epi8(needle as i8);
+                found = _mm_or_si128(found, _mm_cmpeq_epi8(chunk, needle));
+            }
+
+            let mask = _mm_movemask_epi8(found);
+
+            if mask != 0 {
+                return Some(idx + mask.trailing_zeros() as usize);
+            }
+            idx += CHUNK;
+        }
+
+        self.find_scalar(haystack, idx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_needles() {
+        let needles = Needles::new().with(b'<').with(b'&');
+        let haystack = b"Lorem ipsum dolor sit amet, consectetur <adipiscing> elit & more";
+
+        assert_eq!(needles.find(haystack, 0), Some(40));
+        assert_eq!(needles.find(haystack, 41), Some(58));
+        assert_eq!(needles.find(haystack, 59), None);
+        assert_eq!(needles.find(haystack, 100), None);
+        assert_eq!(needles.find(b"short &", 0), Some(6));
+        assert_eq!(Needles::new().find(haystack, 0), None);
+
+        for from in 0..haystack.len() {
+            assert_eq!(
+                needles.find(haystack, from),
+                needles.find_scalar(haystack, from)
+            );
+        }
+    }
+
+    #[test]
+    fn falls_back_with_many_needles() {
+        let mut needles = Needles::new();
+
+        for byte in b'a'..=b'z' {
+            needles = needles.with(byte);
+        }
+        assert_eq!(needles.find(b"123456789012345678901234x", 0), Some(24));
+
+        for byte in b'b'..=b'z' {
+            needles = needles.without(byte);
+        }
+        assert_eq!(needles.len, 1);
+        assert_eq!(
+            needles.find(b"zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzza", 0),
+            Some(34)
+        );
+    }
+}
@@ -0,0 +1,821 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Rendering of any `serde::Serialize` value, by walking it with serializers
+//! that only look at the parts the template needs.
+
+use std::fmt::{self, Write};
+
+use serde::ser::{self, Serialize, Serializer};
+
+#[cfg(feature = "indexes")]
+use crate::content::IndexBasedRender;
+use crate::encoding::Encoder;
+use crate::template::Section;
+use crate::traits::ContentSequence;
+use crate::Content;
+
+/// Renders any value implementing `serde::Serialize` as `Content`, for types
+/// that can't derive it, such as those owned by other crates.
+///
+/// Structs and maps have fields looked up by name, sequences and tuples render
+/// sections for each of their elements, and other values render as variables.
+/// `None` and `()` are falsy, as are `false`, zeros, and empty strings,
+/// sequences and maps. The value is walked anew for every field and section
+/// of the template, without being copied.
+/// ```
+/// # use ramhorns::{Serde, Template};
+/// # use serde_derive::Serialize;
+/// #[derive(Serialize)]
+/// struct Post {
+///     title: String,
+///     tags: Vec<Tag>,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Tag {
+///     name: &'static str,
+/// }
+///
+/// let tpl = Template::new("<h1>{{title}}</h1>{{#tags}}<i>{{name}}</i>{{/tags}}").unwrap();
+/// let post = Post {
+///     title: "Fish & Chips".into(),
+///     tags: vec![Tag { name: "food" }, Tag { name: "uk" }],
+/// };
+///
+/// assert_eq!(
+///     tpl.render(&Serde(&post)),
+///     "<h1>Fish &amp; Chips</h1><i>food</i><i>uk</i>"
+/// );
+/// ```
+pub struct Serde<'a, T: ?Sized>(pub &'a T);
+
+impl<T: ?Sized> Clone for Serde<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Serde<'_, T> {}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Serde<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Serde").field(&self.0).finish()
+    }
+}
+
+impl<T: ?Sized + Serialize> Content for Serde<'_, T> {
+    #[inline]
+    fn is_truthy(&self) -> bool {
+        Node(&self.0).is_truthy()
+    }
+
+    #[inline]
+    fn render_escaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        Node(&self.0).render_escaped(encoder)
+    }
+
+    #[inline]
+    fn render_unescaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        Node(&self.0).render_unescaped(encoder)
+    }
+
+    #[inline]
+    fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        Node(&self.0).render_section(section, encoder)
+    }
+
+    #[inline]
+    fn render_inverse<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        Node(&self.0).render_inverse(section, encoder)
+    }
+
+    #[inline]
+    fn render_field_escaped<E>(
+        &self,
+        hash: u64,
+        name: &str,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        Node(&self.0).render_field_escaped(hash, name, encoder)
+    }
+
+    #[inline]
+    fn render_field_unescaped<E>(
+        &self,
+        hash: u64,
+        name: &str,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        Node(&self.0).render_field_unescaped(hash, name, encoder)
+    }
+
+    #[inline]
+    fn render_field_section<C, E>(
+        &self,
+        hash: u64,
+        name: &str,
+        section: Section<C>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        Node(&self.0).render_field_section(hash, name, section, encoder)
+    }
+
+    #[inline]
+    fn render_field_inverse<C, E>(
+        &self,
+        hash: u64,
+        name: &str,
+        section: Section<C>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        Node(&self.0).render_field_inverse(hash, name, section, encoder)
+    }
+}
+
+/// Value behind `Serde` or any of its parts, with its type erased so that
+/// sections only ever push this one type onto the stack. Otherwise every
+/// type found in the value would multiply the rendering code generated.
+#[derive(Clone, Copy)]
+struct Node<'a>(&'a dyn Erased);
+
+impl Content for Node<'_> {
+    #[inline]
+    fn is_truthy(&self) -> bool {
+        walk(self.0, Probe::default()).is_truthy()
+    }
+
+    fn render_escaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        walk(self.0, Render::new(encoder, true)).finish()
+    }
+
+    fn render_unescaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+        walk(self.0, Render::new(encoder, false)).finish()
+    }
+
+    fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        match walk(self.0, Probe::default()) {
+            Probe::Seq(length) => walk(self.0, Iterate::new(section, encoder, length)).finish(),
+            probe if !probe.is_truthy() => Ok(()),
+            Probe::Map(_) | Probe::Struct => section.with(self).render(encoder),
+            _ => section.render(encoder),
+        }
+    }
+
+    fn render_field_escaped<E>(&self, _: u64, name: &str, encoder: &mut E) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        walk(
+            self.0,
+            Field::new(name, |value| value.render_escaped(encoder)),
+        )
+        .finish()
+    }
+
+    fn render_field_unescaped<E>(
+        &self,
+        _: u64,
+        name: &str,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        E: Encoder,
+    {
+        walk(
+            self.0,
+            Field::new(name, |value| value.render_unescaped(encoder)),
+        )
+        .finish()
+    }
+
+    fn render_field_section<C, E>(
+        &self,
+        _: u64,
+        name: &str,
+        section: Section<C>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        walk(
+            self.0,
+            Field::new(name, |value| value.render_section(section, encoder)),
+        )
+        .finish()
+    }
+
+    fn render_field_inverse<C, E>(
+        &self,
+        _: u64,
+        name: &str,
+        section: Section<C>,
+        encoder: &mut E,
+    ) -> Result<bool, E::Error>
+    where
+        C: ContentSequence,
+        E: Encoder,
+    {
+        walk(
+            self.0,
+            Field::new(name, |value| value.render_inverse(section, encoder)),
+        )
+        .finish()
+    }
+}
+
+/// Walk `value` with the given visitor, returning it when it's done.
+fn walk<V: Visit>(value: &dyn Erased, mut visit: V) -> V {
+    value.walk(&mut visit);
+    visit
+}
+
+/// Object safe counterpart of `Serialize`, only able to be walked.
+trait Erased {
+    fn walk(&self, visit: &mut dyn Visit);
+}
+
+impl<T: ?Sized + Serialize> Erased for T {
+    fn walk(&self, visit: &mut dyn Visit) {
+        let mut walker = Walker {
+            visit,
+            key: String::new(),
+        };
+
+        // Walks are stopped early all the time, the visitor knows why.
+        let _ = self.serialize(&mut walker);
+    }
+}
+
+/// Value that isn't a sequence, map or struct.
+#[derive(Clone, Copy)]
+enum Scalar<'a> {
+    Unit,
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(&'a str),
+    Bytes(&'a [u8]),
+}
+
+impl Scalar<'_> {
+    fn is_truthy(self) -> bool {
+        match self {
+            Scalar::Unit => false,
+            Scalar::Bool(value) => value,
+            Scalar::I64(value) => value.is_truthy(),
+            Scalar::U64(value) => value.is_truthy(),
+            Scalar::I128(value) => value.is_truthy(),
+            Scalar::U128(value) => value.is_truthy(),
+            Scalar::F32(value) => value.is_truthy(),
+            Scalar::F64(value) => value.is_truthy(),
+            Scalar::Char(_) => true,
+            Scalar::Str(value) => !value.is_empty(),
+            Scalar::Bytes(value) => !value.is_empty(),
+        }
+    }
+
+    fn render<E: Encoder>(self, encoder: &mut E, escape: bool) -> Result<(), E::Error> {
+        match self {
+            Scalar::Unit | Scalar::Bytes(_) => Ok(()),
+            Scalar::Bool(value) => value.render_escaped(encoder),
+            Scalar::I64(value) => value.render_escaped(encoder),
+            Scalar::U64(value) => value.render_escaped(encoder),
+            Scalar::I128(value) => value.render_escaped(encoder),
+            Scalar::U128(value) => value.render_escaped(encoder),
+            Scalar::F32(value) => value.render_escaped(encoder),
+            Scalar::F64(value) => value.render_escaped(encoder),
+            Scalar::Char(value) => {
+                Scalar::Str(value.encode_utf8(&mut [0; 4])).render(encoder, escape)
+            }
+            Scalar::Str(value) if escape => encoder.write_escaped(value),
+            Scalar::Str(value) => encoder.write_unescaped(value),
+        }
+    }
+}
+
+/// What to do with the parts of a walked value. Returning `Err(Stop)` ends
+/// the walk, which is what happens to anything not handled.
+trait Visit {
+    fn scalar(&mut self, scalar: Scalar) -> Result<(), Stop>;
+
+    /// Start of a sequence or tuple, of the given length if known.
+    fn seq(&mut self, _len: Option<usize>) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    fn element(&mut self, _value: &dyn Erased) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    /// Start of a map, of the given length if known.
+    fn map(&mut self, _len: Option<usize>) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    /// Start of a struct, treated as a map unless overridden.
+    fn structure(&mut self) -> Result<(), Stop> {
+        self.map(None)
+    }
+
+    fn field(&mut self, _key: &str, _value: &dyn Erased) -> Result<(), Stop> {
+        Err(Stop)
+    }
+}
+
+/// Error ending a walk, either because the visitor is done or the value
+/// failed to serialize.
+#[derive(Debug)]
+struct Stop;
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("stopped walking the value")
+    }
+}
+
+impl std::error::Error for Stop {}
+
+impl ser::Error for Stop {
+    fn custom<T: fmt::Display>(_: T) -> Self {
+        Stop
+    }
+}
+
+/// Shape and truthiness of a value, counting sequences and maps of
+/// unknown length.
+#[derive(Default)]
+enum Probe {
+    #[default]
+    Unit,
+    Scalar(bool),
+    Seq(usize),
+    Map(usize),
+    Struct,
+}
+
+impl Probe {
+    fn is_truthy(&self) -> bool {
+        match *self {
+            Probe::Unit => false,
+            Probe::Scalar(truthy) => truthy,
+            Probe::Seq(len) | Probe::Map(len) => len != 0,
+            Probe::Struct => true,
+        }
+    }
+}
+
+impl Visit for Probe {
+    fn scalar(&mut self, scalar: Scalar) -> Result<(), Stop> {
+        *self = Probe::Scalar(scalar.is_truthy());
+        Err(Stop)
+    }
+
+    fn seq(&mut self, len: Option<usize>) -> Result<(), Stop> {
+        *self = Probe::Seq(len.unwrap_or(0));
+        len.map_or(Ok(()), |_| Err(Stop))
+    }
+
+    fn element(&mut self, _: &dyn Erased) -> Result<(), Stop> {
+        if let Probe::Seq(len) = self {
+            *len += 1;
+        }
+        Ok(())
+    }
+
+    fn map(&mut self, len: Option<usize>) -> Result<(), Stop> {
+        *self = Probe::Map(len.unwrap_or(0));
+        len.map_or(Ok(()), |_| Err(Stop))
+    }
+
+    fn structure(&mut self) -> Result<(), Stop> {
+        *self = Probe::Struct;
+        Err(Stop)
+    }
+
+    fn field(&mut self, _: &str, _: &dyn Erased) -> Result<(), Stop> {
+        if let Probe::Map(len) = self {
+            *len += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Renders a scalar value as a variable.
+struct Render<'e, E: Encoder> {
+    encoder: &'e mut E,
+    escape: bool,
+    result: Result<(), E::Error>,
+}
+
+impl<'e, E: Encoder> Render<'e, E> {
+    fn new(encoder: &'e mut E, escape: bool) -> Self {
+        Render {
+            encoder,
+            escape,
+            result: Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<(), E::Error> {
+        self.result
+    }
+}
+
+impl<E: Encoder> Visit for Render<'_, E> {
+    fn scalar(&mut self, scalar: Scalar) -> Result<(), Stop> {
+        self.result = scalar.render(self.encoder, self.escape);
+        Err(Stop)
+    }
+}
+
+/// Renders a section for each element of a sequence.
+struct Iterate<'s, 'e, C: ContentSequence, E: Encoder> {
+    section: Section<'s, C>,
+    encoder: &'e mut E,
+    #[cfg_attr(not(feature = "indexes"), allow(dead_code))]
+    length: usize,
+    #[cfg_attr(not(feature = "indexes"), allow(dead_code))]
+    index: usize,
+    result: Result<(), E::Error>,
+}
+
+impl<'s, 'e, C: ContentSequence, E: Encoder> Iterate<'s, 'e, C, E> {
+    fn new(section: Section<'s, C>, encoder: &'e mut E, length: usize) -> Self {
+        Iterate {
+            section,
+            encoder,
+            length,
+            index: 0,
+            result: Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<(), E::Error> {
+        self.result
+    }
+}
+
+impl<C: ContentSequence, E: Encoder> Visit for Iterate<'_, '_, C, E> {
+    fn scalar(&mut self, _: Scalar) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    fn seq(&mut self, _: Option<usize>) -> Result<(), Stop> {
+        Ok(())
+    }
+
+    fn element(&mut self, value: &dyn Erased) -> Result<(), Stop> {
+        let item = Node(value);
+
+        #[cfg(feature = "indexes")]
+        let item = IndexBasedRender {
+            length: self.length,
+            index: self.index,
+            item: &item,
+        };
+
+        self.result = item.render_section(self.section, self.encoder);
+        self.index += 1;
+        self.result.as_ref().map_err(|_| Stop).copied()
+    }
+}
+
+/// Renders the field of a map or struct with the given name.
+struct Field<'n, F, Err> {
+    name: &'n str,
+    render: F,
+    result: Option<Result<(), Err>>,
+}
+
+impl<'n, F, Err> Field<'n, F, Err>
+where
+    F: FnMut(Node) -> Result<(), Err>,
+{
+    fn new(name: &'n str, render: F) -> Self {
+        Field {
+            name,
+            render,
+            result: None,
+        }
+    }
+
+    /// Whether the field was found, if it rendered successfully.
+    fn finish(self) -> Result<bool, Err> {
+        self.result.map_or(Ok(false), |result| result.map(|_| true))
+    }
+}
+
+impl<F, Err> Visit for Field<'_, F, Err>
+where
+    F: FnMut(Node) -> Result<(), Err>,
+{
+    fn scalar(&mut self, _: Scalar) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    fn map(&mut self, _: Option<usize>) -> Result<(), Stop> {
+        Ok(())
+    }
+
+    fn field(&mut self, key: &str, value: &dyn Erased) -> Result<(), Stop> {
+        if key != self.name {
+            return Ok(());
+        }
+
+        self.result = Some((self.render)(Node(value)));
+        Err(Stop)
+    }
+}
+
+/// Writes a map key made of a string, character, number or bool.
+struct Key(String);
+
+impl Visit for Key {
+    fn scalar(&mut self, scalar: Scalar) -> Result<(), Stop> {
+        let _ = match scalar {
+            Scalar::Bool(value) => write!(self.0, "{}", value),
+            Scalar::I64(value) => write!(self.0, "{}", value),
+            Scalar::U64(value) => write!(self.0, "{}", value),
+            Scalar::I128(value) => write!(self.0, "{}", value),
+            Scalar::U128(value) => write!(self.0, "{}", value),
+            Scalar::Char(value) => write!(self.0, "{}", value),
+            Scalar::Str(value) => write!(self.0, "{}", value),
+            _ => Ok(()),
+        };
+        Err(Stop)
+    }
+}
+
+/// Serializer passing the parts of a value to the visitor. Options, newtypes
+/// and enum variants are transparent, with unit variants being strings.
+struct Walker<'v> {
+    visit: &'v mut dyn Visit,
+    /// The last key of a map.
+    key: String,
+}
+
+impl Serializer for &mut Walker<'_> {
+    type Ok = ();
+    type Error = Stop;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::I64(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::I64(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::I64(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::I64(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::I128(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::U64(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::U64(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::U64(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::U64(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::U128(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::F32(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::F64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Str(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Bytes(v))
+    }
+
+    fn serialize_none(self) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Unit)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Stop> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Unit)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), Stop> {
+        self.visit.scalar(Scalar::Str(variant))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Stop> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Stop> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, Stop> {
+        self.visit.seq(len).map(|_| self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, Stop> {
+        self.visit.seq(Some(len)).map(|_| self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Self, Stop> {
+        self.visit.seq(Some(len)).map(|_| self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self, Stop> {
+        self.visit.seq(Some(len)).map(|_| self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, Stop> {
+        self.visit.map(len).map(|_| self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Stop> {
+        self.visit.structure().map(|_| self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, Stop> {
+        self.visit.structure().map(|_| self)
+    }
+}
+
+macro_rules! impl_seq {
+    ($( $trait:ident :: $method:ident ),*) => {
+        $(
+            impl ser::$trait for &mut Walker<'_> {
+                type Ok = ();
+                type Error = Stop;
+
+                fn $method<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Stop> {
+                    self.visit.element(&value)
+                }
+
+                fn end(self) -> Result<(), Stop> {
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+impl_seq!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+macro_rules! impl_struct {
+    ($( $trait:ident ),*) => {
+        $(
+            impl ser::$trait for &mut Walker<'_> {
+                type Ok = ();
+                type Error = Stop;
+
+                fn serialize_field<T: ?Sized + Serialize>(
+                    &mut self,
+                    key: &'static str,
+                    value: &T,
+                ) -> Result<(), Stop> {
+                    self.visit.field(key, &value)
+                }
+
+                fn end(self) -> Result<(), Stop> {
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+impl_struct!(SerializeStruct, SerializeStructVariant);
+
+impl ser::SerializeMap for &mut Walker<'_> {
+    type Ok = ();
+    type Error = Stop;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Stop> {
+        let mut buf = std::mem::take(&mut self.key);
+
+        buf.clear();
+        self.key = walk(&key, Key(buf)).0;
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Stop> {
+        self.visit.field(&self.key, &value)
+    }
+
+    fn end(self) -> Result<(), Stop> {
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+use std::hash::BuildHasher;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use beef::Cow;
+
+use crate::{read_file, Error, Ramhorns, Template};
+
+/// Thread-safe variant of [`Ramhorns`](./struct.Ramhorns.html) that can be shared
+/// between threads, eg: through an `Arc`, and loads missing templates on demand.
+///
+/// Templates are handed out as `Arc<Template>`, so a handle obtained from
+/// [`get`](#method.get) stays valid even while the template is being reloaded.
+/// Loaded templates are retrieved under a read lock, so renders never wait for
+/// each other, only for an occasional load.
+///
+/// ```no_run
+/// # use ramhorns::SharedRamhorns;
+/// use std::sync::Arc;
+///
+/// let tpls: Arc<SharedRamhorns> = Arc::new(SharedRamhorns::lazy("./templates").unwrap());
+///
+/// let handler = {
+///     let tpls = tpls.clone();
+///     std::thread::spawn(move || tpls.get("hello.html").unwrap().render(&"I am the content"))
+/// };
+/// let rendered = handler.join().unwrap();
+/// ```
+pub struct SharedRamhorns<H = fnv::FnvBuildHasher> {
+    inner: RwLock<Ramhorns<H>>,
+}
+
+impl<H> fmt::Debug for SharedRamhorns<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedRamhorns")
+            .field("inner", &*self.read())
+            .finish()
+    }
+}
+
+impl<H> From<Ramhorns<H>> for SharedRamhorns<H> {
+    fn from(ramhorns: Ramhorns<H>) -> Self {
+        SharedRamhorns {
+            inner: RwLock::new(ramhorns),
+        }
+    }
+}
+
+impl<H> SharedRamhorns<H> {
+    // Parsing never leaves `Ramhorns` in an inconsistent state, so a panic
+    // on another thread is no reason to stop serving templates.
+    #[inline]
+    fn read(&self) -> RwLockReadGuard<'_, Ramhorns<H>> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[inline]
+    fn write(&self) -> RwLockWriteGuard<'_, Ramhorns<H>> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Unwrap the underlying [`Ramhorns`](./struct.Ramhorns.html) aggregator.
+    pub fn into_inner(self) -> Ramhorns<H> {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<H: BuildHasher + Default> SharedRamhorns<H> {
+    /// Create a new empty aggregator for a given folder. Templates will be loaded
+    /// the first time they are requested with [`get`](#method.get).
+    pub fn lazy<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        Ramhorns::lazy(dir).map(Into::into)
+    }
+
+    /// Loads all the `.html` files as templates from the given folder,
+    /// see [`Ramhorns::from_folder`](./struct.Ramhorns.html#method.from_folder).
+    pub fn from_folder<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        Ramhorns::from_folder(dir).map(Into::into)
+    }
+
+    /// Loads all files with the given extension as templates from the given folder,
+    /// see [`Ramhorns::from_folder_with_extension`](./struct.Ramhorns.html#method.from_folder_with_extension).
+    pub fn from_folder_with_extension<P: AsRef<Path>>(
+        dir: P,
+        extension: &str,
+    ) -> Result<Self, Error> {
+        Ramhorns::from_folder_with_extension(dir, extension).map(Into::into)
+    }
+
+    /// Get the template with the given name. If the template doesn't exist,
+    /// it will be loaded from file and parsed first.
+    pub fn get(&self, name: &str) -> Result<Arc<Template<'static>>, Error> {
+        if let Some(tpl) = self.read().get_shared(name) {
+            return Ok(tpl);
+        }
+
+        let mut inner = self.write();
+
+        // Another thread might have loaded the template while we were waiting
+        // for the lock, `load` will only load it if that's not the case.
+        inner.load(name).cloned()
+    }
+
+    /// Get the template with the given name, if it has already been loaded.
+    pub fn get_loaded(&self, name: &str) -> Option<Arc<Template<'static>>> {
+        self.read().get_shared(name)
+    }
+
+    /// Load the template with the given name from file again, replacing the
+    /// previous version.
+    ///
+    /// The file is read without holding any locks, and handles to the previous
+    /// version remain valid. Templates that include this one as a partial keep
+    /// rendering the version they were parsed with.
+    pub fn reload(&self, name: &str) -> Result<Arc<Template<'static>>, Error> {
+        let (file, path) = {
+            let inner = self.read();
+            let file = inner.file_name(name).into_owned();
+            let path = inner.dir.join(&file);
+
+            (file, path)
+        };
+        let source = read_file(&path, name)?;
+
+        let mut inner = self.write();
+
+        inner.insert(source, Cow::owned(file))?;
+        inner.load(name).cloned()
+    }
+
+    /// Insert a template parsed from `src` with the name `name`.
+    /// If a template with this name is present, it gets replaced.
+    ///
+    /// # Warning
+    /// This can load partials from an arbitrary path. Use only with trusted source.
+    pub fn insert<S, T>(&self, src: S, name: T) -> Result<(), Error>
+    where
+        S: Into<Cow<'static, str>>,
+        T: Into<Cow<'static, str>>,
+    {
+        self.write().insert(src, name)
+    }
+
+    /// Remove the template with the given name, returning it if it was present.
+    pub fn remove(&self, name: &str) -> Option<Arc<Template<'static>>> {
+        self.write().remove(name)
+    }
+
+    /// Check if a template with the given name has been loaded.
+    pub fn contains(&self, name: &str) -> bool {
+        self.read().contains(name)
+    }
+
+    /// Retain only the templates for which the predicate returns `true`.
+    pub fn retain<F>(&self, f: F)
+    where
+        F: FnMut(&str, &Template<'static>) -> bool,
+    {
+        self.write().retain(f)
+    }
+}
@@ -0,0 +1,316 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use arrayvec::ArrayVec;
+
+use crate::encoding::EscapeContext;
+
+/// Tag and attribute names we care about are short, longer ones are cut off.
+type Name = ArrayVec<u8, 16>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Text,
+    /// After `<`
+    TagOpen,
+    /// After `<!`, counting the dashes of a comment opening
+    Declaration(u8),
+    /// Inside `<!-- -->`, counting the dashes of the comment closing
+    Comment(u8),
+    /// End tags, doctypes and such, ignored until `>`
+    Bogus,
+    TagName,
+    BeforeAttributeName,
+    AttributeName,
+    AfterAttributeName,
+    BeforeValue,
+    /// Attribute value, with the quote it's delimited with, if any
+    Value(Option<u8>),
+    /// Contents of a `<script>` or `<style>` element, with the number of bytes
+    /// matched of its closing tag
+    RawText(Element, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Element {
+    Script,
+    Style,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Attribute {
+    Url,
+    Script,
+    Style,
+    Other,
+}
+
+/// Minimal HTML tokenizer, fed with the HTML between tags of a template in order
+/// to know the context each tag is interpolated into.
+#[derive(Debug, Clone)]
+pub(crate) struct HtmlScanner {
+    state: State,
+    name: Name,
+    element: Element,
+    attribute: Attribute,
+    /// Nothing has been written to the current attribute value yet.
+    value_start: bool,
+}
+
+impl Default for HtmlScanner {
+    fn default() -> Self {
+        HtmlScanner {
+            state: State::Text,
+            name: Name::new(),
+            element: Element::Other,
+            attribute: Attribute::Other,
+            value_start: false,
+        }
+    }
+}
+
+impl HtmlScanner {
+    /// Context for a tag interpolated at the current position.
+    pub fn context(&self) -> EscapeContext {
+        let quoted = match self.state {
+            State::RawText(Element::Script, _) => return EscapeContext::Script,
+            State::RawText(Element::Style, _) => return EscapeContext::Style,
+            State::TagOpen
+            | State::TagName
+            | State::BeforeAttributeName
+            | State::AttributeName
+            | State::AfterAttributeName => return EscapeContext::UnquotedAttribute,
+            State::BeforeValue => false,
+            State::Value(quote) => quote.is_some(),
+            _ => return EscapeContext::Text,
+        };
+
+        match self.attribute {
+            Attribute::Url if self.value_start || self.state == State::BeforeValue => {
+                EscapeContext::Url
+            }
+            Attribute::Script => EscapeContext::Script,
+            Attribute::Style => EscapeContext::Style,
+            _ if quoted => EscapeContext::Attribute,
+            _ => EscapeContext::UnquotedAttribute,
+        }
+    }
+
+    /// Advance past a value interpolated at the current position.
+    pub fn interpolate(&mut self) {
+        match self.state {
+            State::BeforeValue => self.state = State::Value(None),
+            State::Value(_) => {}
+            _ => return,
+        }
+        self.value_start = false;
+    }
+
+    /// Advance past a chunk of HTML.
+    pub fn scan(&mut self, html: &str) {
+        for byte in html.bytes() {
+            self.state = self.next(byte);
+        }
+    }
+
+    fn next(&mut self, byte: u8) -> State {
+        match self.state {
+            State::Text => match byte {
+                b'<' => State::TagOpen,
+                _ => State::Text,
+            },
+            State::TagOpen => match byte {
+                b'!' => State::Declaration(0),
+                b'/' | b'?' => State::Bogus,
+                b'<' => State::TagOpen,
+                byte if byte.is_ascii_alphabetic() => {
+                    self.name.clear();
+                    self.push_name(byte);
+                    State::TagName
+                }
+                _ => State::Text,
+            },
+            State::Declaration(dashes) => match byte {
+                b'-' if dashes == 1 => State::Comment(0),
+                b'-' if dashes == 0 => State::Declaration(1),
+                b'>' => State::Text,
+                _ => State::Bogus,
+            },
+            State::Comment(dashes) => match byte {
+                b'>' if dashes >= 2 => State::Text,
+                b'-' => State::Comment(dashes.saturating_add(1)),
+                _ => State::Comment(0),
+            },
+            State::Bogus => match byte {
+                b'>' => State::Text,
+                _ => State::Bogus,
+            },
+            State::TagName => match byte {
+                b'>' => self.end_tag(),
+                b'/' => {
+                    self.element = self.tag_element();
+                    State::BeforeAttributeName
+                }
+                byte if byte.is_ascii_whitespace() => {
+                    self.element = self.tag_element();
+                    State::BeforeAttributeName
+                }
+                byte => {
+                    self.push_name(byte);
+                    State::TagName
+                }
+            },
+            State::BeforeAttributeName => match byte {
+                b'>' => self.end_tag(),
+                b'/' => State::BeforeAttributeName,
+                byte if byte.is_ascii_whitespace() => State::BeforeAttributeName,
+                byte => self.start_attribute(byte),
+            },
+            State::AttributeName => match byte {
+                b'>' => self.end_tag(),
+                b'/' => State::BeforeAttributeName,
+                b'=' => self.start_value(),
+                byte if byte.is_ascii_whitespace() => State::AfterAttributeName,
+                byte => {
+                    self.push_name(byte);
+                    State::AttributeName
+                }
+            },
+            State::AfterAttributeName => match byte {
+                b'>' => self.end_tag(),
+                b'/' => State::BeforeAttributeName,
+                b'=' => self.start_value(),
+                byte if byte.is_ascii_whitespace() => State::AfterAttributeName,
+                byte => self.start_attribute(byte),
+            },
+            State::BeforeValue => match byte {
+                b'>' => self.end_tag(),
+                b'"' | b'\'' => {
+                    self.value_start = true;
+                    State::Value(Some(byte))
+                }
+                byte if byte.is_ascii_whitespace() => State::BeforeValue,
+                _ => {
+                    self.value_start = false;
+                    State::Value(None)
+                }
+            },
+            State::Value(Some(quote)) if byte == quote => State::BeforeAttributeName,
+            State::Value(None) if byte == b'>' => self.end_tag(),
+            State::Value(None) if byte.is_ascii_whitespace() => State::BeforeAttributeName,
+            State::Value(quote) => {
+                self.value_start = false;
+                State::Value(quote)
+            }
+            State::RawText(element, matched) => {
+                let closing: &[u8] = match element {
+                    Element::Script => b"</script",
+                    _ => b"</style",
+                };
+
+                if byte.to_ascii_lowercase() == closing[matched as usize] {
+                    match matched as usize + 1 == closing.len() {
+                        true => State::Bogus,
+                        false => State::RawText(element, matched + 1),
+                    }
+                } else {
+                    State::RawText(element, (byte == b'<') as u8)
+                }
+            }
+        }
+    }
+
+    fn push_name(&mut self, byte: u8) {
+        let _ = self.name.try_push(byte.to_ascii_lowercase());
+    }
+
+    fn tag_element(&self) -> Element {
+        match &self.name[..] {
+            b"script" => Element::Script,
+            b"style" => Element::Style,
+            _ => Element::Other,
+        }
+    }
+
+    fn end_tag(&mut self) -> State {
+        if self.state == State::TagName {
+            self.element = self.tag_element();
+        }
+
+        match self.element {
+            Element::Other => State::Text,
+            element => State::RawText(element, 0),
+        }
+    }
+
+    fn start_attribute(&mut self, byte: u8) -> State {
+        self.name.clear();
+        self.push_name(byte);
+        State::AttributeName
+    }
+
+    fn start_value(&mut self) -> State {
+        self.attribute = match &self.name[..] {
+            b"href" | b"src" | b"action" | b"formaction" | b"cite" | b"data" | b"poster"
+            | b"background" | b"longdesc" | b"manifest" | b"srcset" | b"xlink:href" => {
+                Attribute::Url
+            }
+            b"style" => Attribute::Style,
+            name if name.starts_with(b"on") => Attribute::Script,
+            _ => Attribute::Other,
+        };
+        State::BeforeValue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn context(html: &str) -> EscapeContext {
+        let mut scanner = HtmlScanner::default();
+        scanner.scan(html);
+        scanner.context()
+    }
+
+    #[test]
+    fn tracks_html_contexts() {
+        assert_eq!(context("<p>Hello "), EscapeContext::Text);
+        assert_eq!(context("<p title=\"Hello "), EscapeContext::Attribute);
+        assert_eq!(context("<p title='"), EscapeContext::Attribute);
+        assert_eq!(context("<p title="), EscapeContext::UnquotedAttribute);
+        assert_eq!(context("<p "), EscapeContext::UnquotedAttribute);
+        assert_eq!(context("<a href=\""), EscapeContext::Url);
+        assert_eq!(context("<a HREF="), EscapeContext::Url);
+        assert_eq!(context("<a href=\"/posts/"), EscapeContext::Attribute);
+        assert_eq!(context("<a href=\"/\">"), EscapeContext::Text);
+        assert_eq!(context("<button onclick=\"go('"), EscapeContext::Script);
+        assert_eq!(context("<p style=\"color: "), EscapeContext::Style);
+        assert_eq!(context("<script>var x = '"), EscapeContext::Script);
+        assert_eq!(context("<script type=\"module\">"), EscapeContext::Script);
+        assert_eq!(context("<style>p { color: "), EscapeContext::Style);
+        assert_eq!(context("<script>var x = '</p>"), EscapeContext::Script);
+        assert_eq!(context("<script></SCRIPT >"), EscapeContext::Text);
+        assert_eq!(context("<!-- <script> -->"), EscapeContext::Text);
+        assert_eq!(context("</script>"), EscapeContext::Text);
+    }
+
+    #[test]
+    fn interpolated_values_continue_attributes() {
+        let mut scanner = HtmlScanner::default();
+
+        scanner.scan("<a href=");
+        assert_eq!(scanner.context(), EscapeContext::Url);
+
+        scanner.interpolate();
+        assert_eq!(scanner.context(), EscapeContext::UnquotedAttribute);
+
+        scanner.scan(" title=\"");
+        assert_eq!(scanner.context(), EscapeContext::Attribute);
+
+        scanner.scan("\">");
+        assert_eq!(scanner.context(), EscapeContext::Text);
+    }
+}
@@ -7,14 +7,18 @@ use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io;
 use std::path::Path;
+use std::sync::Arc;
 
 use beef::Cow;
 use fnv::FnvHasher;
 
-use crate::encoding::{Encoder, EscapingIOEncoder};
+use crate::encoding::{Encoder, EscapeContext, EscapingFmtEncoder, EscapingIOEncoder};
+#[cfg(feature = "markdown")]
+use crate::MarkdownOptions;
 use crate::Partials;
-use crate::{Content, Error};
+use crate::{Content, Error, Escape, Escaper, Escaping};
 
+mod html;
 mod parse;
 mod section;
 
@@ -35,6 +39,17 @@ pub struct Template<'tpl> {
 
     /// Source from which this template was parsed.
     source: Cow<'tpl, str>,
+
+    /// Partials whose blocks were inlined into this template. Holding on to them
+    /// keeps their sources alive for as long as the blocks borrowing from them.
+    includes: Vec<Arc<Template<'tpl>>>,
+
+    /// Strategy for escaping values of `{{escaped}}` tags.
+    escape: Escape,
+
+    /// Extensions for rendering `{{#md}}` sections, if they are enabled.
+    #[cfg(feature = "markdown")]
+    markdown: Option<MarkdownOptions>,
 }
 
 impl<'tpl> fmt::Debug for Template<'tpl> {
@@ -71,6 +86,10 @@ impl<'tpl> Template<'tpl> {
             blocks: Vec::with_capacity(16),
             capacity_hint: 0,
             source,
+            includes: Vec::new(),
+            escape: Escape::Html,
+            #[cfg(feature = "markdown")]
+            markdown: None,
         };
 
         let last = tpl.parse(unsafe_source, partials)?;
@@ -101,6 +120,37 @@ impl<'tpl> Template<'tpl> {
         buf
     }
 
+    /// Render this `Template` with a given `Content` to a `String`, escaping
+    /// values with the given `Escaper` instead of the one set for this `Template`.
+    /// ```
+    /// # use ramhorns::{Content, Escape, Template};
+    /// #[derive(Content)]
+    /// struct Row<'a> {
+    ///     id: u32,
+    ///     name: &'a str,
+    /// }
+    ///
+    /// let tpl = Template::new("{{id}},{{name}}").unwrap();
+    /// let row = Row { id: 1, name: "Hirsz, Maciej" };
+    ///
+    /// assert_eq!(tpl.render_with_escaper(&row, Escape::Csv), "1,\"Hirsz, Maciej\"");
+    /// ```
+    pub fn render_with_escaper<C, S>(&self, content: &C, escaper: S) -> String
+    where
+        C: Content,
+        S: Escaper,
+    {
+        let mut capacity = content.capacity_hint(self);
+        capacity += capacity / 4;
+
+        let mut buf = String::with_capacity(capacity);
+
+        // Ignore the result, cannot fail
+        let _ = self.render_blocks(&mut Escaping::new(&mut buf, escaper), content);
+
+        buf
+    }
+
     /// Render this `Template` with a given `Content` to a writer.
     pub fn render_to_writer<W, C>(&self, writer: &mut W, content: &C) -> io::Result<()>
     where
@@ -124,19 +174,152 @@ impl<'tpl> Template<'tpl> {
         self.render_with_encoder(&mut encoder, content)
     }
 
+    /// Render this `Template` with a given `Content` to a `tokio::io::AsyncWrite`.
+    /// The output is written out in chunks of 8 KiB while rendering, as far as the
+    /// writer accepts them without waiting, and the rest is awaited once rendering
+    /// is done, see [`AsyncWriteEncoder`](./encoding/struct.AsyncWriteEncoder.html).
+    #[cfg(feature = "tokio")]
+    pub async fn render_to_async_writer<W, C>(&self, writer: &mut W, content: &C) -> io::Result<()>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+        C: Content,
+    {
+        let mut encoder = crate::encoding::AsyncWriteEncoder::new(writer);
+        self.render_with_encoder(&mut encoder, content)?;
+        encoder.flush().await
+    }
+
+    /// Bind a `Content` to this `Template`, getting a value that renders the
+    /// template when formatted with `Display`, without allocating a `String`.
+    /// ```
+    /// # use ramhorns::{Content, Template};
+    /// #[derive(Content)]
+    /// struct Post<'a> {
+    ///     title: &'a str,
+    /// }
+    ///
+    /// let tpl = Template::new("<h1>{{title}}</h1>").unwrap();
+    /// let post = Post { title: "Fish & Chips" };
+    ///
+    /// assert_eq!(format!("Rendered: {}", tpl.bind(&post)), "Rendered: <h1>Fish &amp; Chips</h1>");
+    /// ```
+    pub fn bind<'a, C: Content>(&'a self, content: &'a C) -> Bound<'a, 'tpl, C> {
+        Bound { tpl: self, content }
+    }
+
     /// Render this `Template` with a given `Content` and a custom `Encoder`
     pub fn render_with_encoder<E, C>(&self, encoder: &mut E, content: &C) -> Result<(), E::Error>
     where
         E: Encoder,
         C: Content,
     {
-        Section::new(&self.blocks).with(content).render(encoder)
+        match self.escape {
+            Escape::Html => self.render_blocks(encoder, content),
+            escape => self.render_blocks(&mut Escaping::new(encoder, escape), content),
+        }
+    }
+
+    #[inline]
+    fn render_blocks<E, C>(&self, encoder: &mut E, content: &C) -> Result<(), E::Error>
+    where
+        E: Encoder,
+        C: Content,
+    {
+        let section = Section::new(&self.blocks);
+        #[cfg(feature = "markdown")]
+        let section = section.with_markdown(self.markdown, self.escape);
+
+        section.with(content).render_blocks(encoder)
+    }
+
+    /// Strategy used for escaping values of `{{escaped}}` tags, HTML by default.
+    pub fn escape(&self) -> Escape {
+        self.escape
+    }
+
+    /// Set the strategy used for escaping values of `{{escaped}}` tags.
+    pub fn set_escape(&mut self, escape: Escape) {
+        self.escape = escape;
+    }
+
+    /// Use the given strategy for escaping values of `{{escaped}}` tags.
+    /// ```
+    /// # use ramhorns::{Content, Escape, Template};
+    /// #[derive(Content)]
+    /// struct Chapter<'a> {
+    ///     title: &'a str,
+    /// }
+    ///
+    /// let tpl = Template::new("\\section{ {{title}} }").unwrap().with_escape(Escape::Latex);
+    ///
+    /// assert_eq!(tpl.render(&Chapter { title: "50% off" }), "\\section{ 50\\% off }");
+    /// ```
+    pub fn with_escape(mut self, escape: Escape) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Markdown extensions used for rendering `{{#md}}` sections, `None` unless
+    /// they have been enabled.
+    #[cfg(feature = "markdown")]
+    pub fn markdown(&self) -> Option<MarkdownOptions> {
+        self.markdown
+    }
+
+    /// Enable `{{#md}}` sections, rendered with the given Markdown extensions.
+    #[cfg(feature = "markdown")]
+    pub fn set_markdown(&mut self, markdown: MarkdownOptions) {
+        self.markdown = Some(markdown);
+    }
+
+    /// Render the contents of `{{#md}}` sections as Markdown with the given
+    /// extensions, see [`MarkdownOptions`](./struct.MarkdownOptions.html).
+    ///
+    /// Without it, `{{#md}}` is an ordinary section, rendered with the field `md`
+    /// of the `Content`.
+    #[cfg(feature = "markdown")]
+    pub fn with_markdown(mut self, markdown: MarkdownOptions) -> Self {
+        self.markdown = Some(markdown);
+        self
     }
 
     /// Get a reference to a source this `Template` was created from.
     pub fn source(&self) -> &str {
         &self.source
     }
+
+    /// Whether any partials were included into this template.
+    pub(crate) fn has_includes(&self) -> bool {
+        !self.includes.is_empty()
+    }
+}
+
+/// A `Template` bound to a `Content` with [`Template::bind`](./struct.Template.html#method.bind),
+/// rendering the template when formatted with `Display`.
+pub struct Bound<'a, 'tpl, C> {
+    tpl: &'a Template<'tpl>,
+    content: &'a C,
+}
+
+impl<C> Clone for Bound<'_, '_, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for Bound<'_, '_, C> {}
+
+impl<C> fmt::Debug for Bound<'_, '_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bound").field("tpl", &self.tpl).finish()
+    }
+}
+
+impl<C: Content> fmt::Display for Bound<'_, '_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tpl
+            .render_with_encoder(&mut EscapingFmtEncoder::new(f), self.content)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -146,6 +329,8 @@ pub(crate) struct Block<'tpl> {
     hash: u64,
     tag: Tag,
     children: u32,
+    /// HTML context the value of an escaped tag is interpolated into.
+    context: EscapeContext,
 }
 
 #[inline]
@@ -164,6 +349,7 @@ impl<'tpl> Block<'tpl> {
             hash: hash_name(name),
             tag,
             children: 0,
+            context: EscapeContext::Text,
         }
     }
 
@@ -176,6 +362,7 @@ impl<'tpl> Block<'tpl> {
             hash: 0,
             tag,
             children: 0,
+            context: EscapeContext::Text,
         }
     }
     /// Get the index if this block refers to a section index.
@@ -192,7 +379,7 @@ impl<'tpl> Block<'tpl> {
 struct NoPartials;
 
 impl<'tpl> Partials<'tpl> for NoPartials {
-    fn get_partial(&mut self, _name: &'tpl str) -> Result<&Template<'tpl>, Error> {
+    fn get_partial(&mut self, _name: &'tpl str) -> Result<Arc<Template<'tpl>>, Error> {
         Err(Error::PartialsDisabled)
     }
 }
@@ -225,6 +412,7 @@ mod test {
                 hash: 2271575940368597870,
                 tag: Tag::Escaped,
                 children: 0,
+                context: EscapeContext::Text,
             }
         );
     }
@@ -7,6 +7,7 @@ use logos::Logos;
 #[cfg(feature = "indexes")]
 use std::convert::TryFrom;
 
+use super::html::HtmlScanner;
 use super::{hash_name, Block, Error, Template};
 use crate::Partials;
 
@@ -46,6 +47,10 @@ pub enum Tag {
     #[cfg(feature = "indexes")]
     Indexed(Indexed),
 
+    /// `{{#md}}` section opening tag, rendering its contents as Markdown if enabled
+    #[cfg(feature = "markdown")]
+    Markdown,
+
     /// `{{!comment}}` tag
     #[token("{{!")]
     Comment,
@@ -161,6 +166,7 @@ impl<'tpl> Template<'tpl> {
         let mut last = 0;
         let mut lex = Tag::lexer(source);
         let mut stack = ArrayVec::<usize, 16>::new();
+        let mut html_scanner = HtmlScanner::default();
 
         while let Some(tag) = lex.next() {
             let tag = tag?;
@@ -172,6 +178,7 @@ impl<'tpl> Template<'tpl> {
             // let html = &lex.before()[last..];
             let mut html = &lex.source()[last..lex.span().start];
             self.capacity_hint += html.len();
+            html_scanner.scan(html);
 
             // Morphing the lexer to match the closing
             // braces and grab the name
@@ -193,7 +200,12 @@ impl<'tpl> Template<'tpl> {
                                 html = "";
                             }
                             Some(Ok(Closing::Match)) => {
-                                self.blocks.push(Block::new(html, name, tag));
+                                let mut block = Block::new(html, name, tag);
+                                if tag == Tag::Escaped {
+                                    block.context = html_scanner.context();
+                                }
+                                html_scanner.interpolate();
+                                self.blocks.push(block);
                                 break;
                             }
                             _ => return Err(Error::UnclosedTag),
@@ -225,6 +237,13 @@ impl<'tpl> Template<'tpl> {
                                 }
                                 None => tag,
                             };
+                            #[cfg(feature = "markdown")]
+                            let tag = match name {
+                                "md" if tag == Tag::Section && self.blocks.len() == tail_idx => {
+                                    Tag::Markdown
+                                }
+                                _ => tag,
+                            };
                             self.blocks.push(Block::new(html, name, tag));
                             break;
                         }
@@ -271,8 +290,20 @@ impl<'tpl> Template<'tpl> {
 
                     self.blocks.push(Block::nameless(html, tag));
                     let partial = partials.get_partial(name)?;
-                    self.blocks.extend_from_slice(&partial.blocks);
+                    // The partial was parsed on its own, so contexts of its tags
+                    // depend on where it's included
+                    for &(mut block) in partial.blocks.iter() {
+                        html_scanner.scan(block.html);
+                        if block.tag == Tag::Escaped {
+                            block.context = html_scanner.context();
+                        }
+                        if matches!(block.tag, Tag::Escaped | Tag::Unescaped) {
+                            html_scanner.interpolate();
+                        }
+                        self.blocks.push(block);
+                    }
                     self.capacity_hint += partial.capacity_hint;
+                    self.includes.push(partial);
                 }
                 _ => {
                     loop {
@@ -3,11 +3,15 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use super::{Block, Tag};
-use crate::encoding::Encoder;
+use crate::encoding::{ContextEncoder, Encoder, EscapeContext};
+#[cfg(feature = "markdown")]
+use crate::markdown::{self, MarkdownOptions};
 #[cfg(feature = "indexes")]
 use crate::template::Indexed;
 use crate::traits::{Combine, ContentSequence};
 use crate::Content;
+#[cfg(feature = "markdown")]
+use crate::Escape;
 use std::ops::Range;
 
 /// A section of a `Template` that can be rendered individually, usually delimited by
@@ -16,6 +20,12 @@ use std::ops::Range;
 pub struct Section<'section, Contents: ContentSequence> {
     blocks: &'section [Block<'section>],
     contents: Contents,
+    /// Extensions for rendering `{{#md}}` sections, if they are enabled.
+    #[cfg(feature = "markdown")]
+    markdown: Option<MarkdownOptions>,
+    /// Strategy for escaping values inside of `{{#md}}` sections.
+    #[cfg(feature = "markdown")]
+    escape: Escape,
 }
 
 /// Necessary so that the warning of very complex type created when compiling
@@ -28,6 +38,22 @@ impl<'section> Section<'section, ()> {
         Self {
             blocks,
             contents: (),
+            #[cfg(feature = "markdown")]
+            markdown: None,
+            #[cfg(feature = "markdown")]
+            escape: Escape::Html,
+        }
+    }
+
+    /// Render `{{#md}}` sections with the given Markdown extensions, if enabled,
+    /// escaping values inside of them with the same strategy as the rest of the template.
+    #[cfg(feature = "markdown")]
+    #[inline]
+    pub(crate) fn with_markdown(self, markdown: Option<MarkdownOptions>, escape: Escape) -> Self {
+        Self {
+            markdown,
+            escape,
+            ..self
         }
     }
 }
@@ -41,6 +67,10 @@ where
         Self {
             blocks: &self.blocks[range],
             contents: self.contents,
+            #[cfg(feature = "markdown")]
+            markdown: self.markdown,
+            #[cfg(feature = "markdown")]
+            escape: self.escape,
         }
     }
 
@@ -54,6 +84,10 @@ where
         Section {
             blocks: self.blocks,
             contents: self.contents.combine(content),
+            #[cfg(feature = "markdown")]
+            markdown: self.markdown,
+            #[cfg(feature = "markdown")]
+            escape: self.escape,
         }
     }
 
@@ -63,6 +97,10 @@ where
         Section {
             blocks: self.blocks,
             contents: self.contents.crawl_back(),
+            #[cfg(feature = "markdown")]
+            markdown: self.markdown,
+            #[cfg(feature = "markdown")]
+            escape: self.escape,
         }
     }
 
@@ -72,6 +110,10 @@ where
         Section {
             blocks: &self.blocks[1..],
             contents: self.contents,
+            #[cfg(feature = "markdown")]
+            markdown: self.markdown,
+            #[cfg(feature = "markdown")]
+            escape: self.escape,
         }
     }
 
@@ -82,8 +124,19 @@ where
         self.blocks.first().and_then(|b| b.index())
     }
 
-    /// Render this section once to the provided `Encoder`.
+    /// Render this section once to the provided `Encoder`, counting it as an
+    /// iteration with [`Encoder::visit_iteration`](../encoding/trait.Encoder.html#method.visit_iteration).
     pub fn render<E>(&self, encoder: &mut E) -> Result<(), E::Error>
+    where
+        E: Encoder,
+    {
+        encoder.visit_iteration()?;
+        self.render_blocks(encoder)
+    }
+
+    /// Render the blocks of this section, such as the whole template, without
+    /// counting it as an iteration.
+    pub(crate) fn render_blocks<E>(&self, encoder: &mut E) -> Result<(), E::Error>
     where
         E: Encoder,
     {
@@ -92,13 +145,21 @@ where
         while let Some(block) = self.blocks.get(index) {
             index += 1;
 
+            encoder.visit_block()?;
             encoder.write_unescaped(block.html)?;
 
             match &block.tag {
-                Tag::Escaped => {
+                Tag::Escaped if block.context == EscapeContext::Text => {
                     self.contents
                         .render_field_escaped(block.hash, block.name, encoder)?;
                 }
+                Tag::Escaped => {
+                    self.contents.render_field_escaped(
+                        block.hash,
+                        block.name,
+                        &mut ContextEncoder::new(encoder, block.context),
+                    )?;
+                }
                 Tag::Unescaped => {
                     self.contents
                         .render_field_unescaped(block.hash, block.name, encoder)?;
@@ -121,6 +182,22 @@ where
                     )?;
                     index += block.children as usize;
                 }
+                #[cfg(feature = "markdown")]
+                Tag::Markdown => {
+                    let section = self.slice(index..index + block.children as usize);
+
+                    match self.markdown {
+                        Some(markdown) => {
+                            markdown::render_section(section, markdown, self.escape, encoder)?
+                        }
+                        // Unless enabled, `{{#md}}` is a section like any other
+                        None => {
+                            self.contents
+                                .render_field_section(block.hash, block.name, section, encoder)?;
+                        }
+                    }
+                    index += block.children as usize;
+                }
                 #[cfg(feature = "indexes")]
                 Tag::Indexed(indexed) => {
                     self.contents.render_index_section(
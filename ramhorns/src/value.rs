@@ -0,0 +1,290 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! `Content` for dynamically typed value trees, such as parsed JSON, TOML or
+//! YAML, following the truthiness of the types their values map to.
+
+use crate::encoding::Encoder;
+#[cfg(feature = "indexes")]
+use crate::template::Indexed;
+use crate::template::{Section, Template};
+use crate::traits::ContentSequence;
+use crate::Content;
+
+/// Implements `Content` for a value tree enum by delegating every method to
+/// the `Content` of what each of its variants holds, with variants that hold
+/// no value mapping to `()`.
+macro_rules! impl_value_types {
+    ($( $ty:ty { $( $variant:pat => $inner:expr ),* $(,)? } )*) => {
+        $(
+            impl Content for $ty {
+                #[inline]
+                fn is_truthy(&self) -> bool {
+                    match self {
+                        $( $variant => Content::is_truthy($inner), )*
+                    }
+                }
+
+                #[inline]
+                fn capacity_hint(&self, tpl: &Template) -> usize {
+                    match self {
+                        $( $variant => Content::capacity_hint($inner, tpl), )*
+                    }
+                }
+
+                #[inline]
+                fn render_escaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+                    match self {
+                        $( $variant => Content::render_escaped($inner, encoder), )*
+                    }
+                }
+
+                #[inline]
+                fn render_unescaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+                    match self {
+                        $( $variant => Content::render_unescaped($inner, encoder), )*
+                    }
+                }
+
+                #[inline]
+                fn render_section<C, E>(
+                    &self,
+                    section: Section<C>,
+                    encoder: &mut E,
+                ) -> Result<(), E::Error>
+                where
+                    C: ContentSequence,
+                    E: Encoder,
+                {
+                    match self {
+                        $( $variant => Content::render_section($inner, section, encoder), )*
+                    }
+                }
+
+                #[inline]
+                fn render_inverse<C, E>(
+                    &self,
+                    section: Section<C>,
+                    encoder: &mut E,
+                ) -> Result<(), E::Error>
+                where
+                    C: ContentSequence,
+                    E: Encoder,
+                {
+                    match self {
+                        $( $variant => Content::render_inverse($inner, section, encoder), )*
+                    }
+                }
+
+                #[inline]
+                fn render_field_escaped<E: Encoder>(
+                    &self,
+                    hash: u64,
+                    name: &str,
+                    encoder: &mut E,
+                ) -> Result<bool, E::Error> {
+                    match self {
+                        $( $variant => {
+                            Content::render_field_escaped($inner, hash, name, encoder)
+                        } )*
+                    }
+                }
+
+                #[inline]
+                fn render_field_unescaped<E: Encoder>(
+                    &self,
+                    hash: u64,
+                    name: &str,
+                    encoder: &mut E,
+                ) -> Result<bool, E::Error> {
+                    match self {
+                        $( $variant => {
+                            Content::render_field_unescaped($inner, hash, name, encoder)
+                        } )*
+                    }
+                }
+
+                #[inline]
+                fn render_field_section<C, E>(
+                    &self,
+                    hash: u64,
+                    name: &str,
+                    section: Section<C>,
+                    encoder: &mut E,
+                ) -> Result<bool, E::Error>
+                where
+                    C: ContentSequence,
+                    E: Encoder,
+                {
+                    match self {
+                        $( $variant => {
+                            Content::render_field_section($inner, hash, name, section, encoder)
+                        } )*
+                    }
+                }
+
+                #[inline]
+                fn render_field_inverse<C, E>(
+                    &self,
+                    hash: u64,
+                    name: &str,
+                    section: Section<C>,
+                    encoder: &mut E,
+                ) -> Result<bool, E::Error>
+                where
+                    C: ContentSequence,
+                    E: Encoder,
+                {
+                    match self {
+                        $( $variant => {
+                            Content::render_field_inverse($inner, hash, name, section, encoder)
+                        } )*
+                    }
+                }
+
+                #[cfg(feature = "indexes")]
+                #[inline]
+                fn render_index_section<'section, P, E>(
+                    &self,
+                    indexed: &Indexed,
+                    section: Section<'section, P>,
+                    encoder: &mut E,
+                ) -> Result<bool, E::Error>
+                where
+                    P: ContentSequence,
+                    E: Encoder,
+                {
+                    match self {
+                        $( $variant => {
+                            Content::render_index_section($inner, indexed, section, encoder)
+                        } )*
+                    }
+                }
+            }
+        )*
+    };
+}
+
+/// Implements `Content` for the number types of value trees, rendering them
+/// like the primitive number they fit in.
+#[cfg(any(feature = "serde_json", feature = "serde_norway"))]
+macro_rules! impl_number_values {
+    ($( $ty:ty ),*) => {
+        $(
+            impl Content for $ty {
+                #[inline]
+                fn is_truthy(&self) -> bool {
+                    if let Some(n) = self.as_u64() {
+                        n.is_truthy()
+                    } else if let Some(n) = self.as_i64() {
+                        n.is_truthy()
+                    } else {
+                        self.as_f64().is_some_and(|n| n.is_truthy())
+                    }
+                }
+
+                #[inline]
+                fn capacity_hint(&self, _tpl: &Template) -> usize {
+                    5
+                }
+
+                #[inline]
+                fn render_escaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+                    if let Some(n) = self.as_u64() {
+                        n.render_escaped(encoder)
+                    } else if let Some(n) = self.as_i64() {
+                        n.render_escaped(encoder)
+                    } else if let Some(n) = self.as_f64() {
+                        n.render_escaped(encoder)
+                    } else {
+                        // Nothing to escape here
+                        encoder.format_unescaped(self)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "serde_json")]
+mod json {
+    use super::*;
+    use serde_json::{Map, Number, Value};
+
+    impl_value_types! {
+        Value {
+            Value::Null => &(),
+            Value::Bool(v) => v,
+            Value::Number(v) => v,
+            Value::String(v) => v,
+            Value::Array(v) => v,
+            Value::Object(v) => v,
+        }
+    }
+
+    impl Content for Map<String, Value> {
+        impl_map_methods!();
+    }
+
+    impl_number_values!(Number);
+}
+
+#[cfg(feature = "serde_norway")]
+mod yaml {
+    use super::*;
+    use serde_norway::{Mapping, Number, Value};
+
+    impl_value_types! {
+        Value {
+            Value::Null => &(),
+            Value::Bool(v) => v,
+            Value::Number(v) => v,
+            Value::String(v) => v,
+            Value::Sequence(v) => v,
+            Value::Mapping(v) => v,
+            Value::Tagged(v) => &v.value,
+        }
+    }
+
+    impl Content for Mapping {
+        impl_map_methods!();
+    }
+
+    impl_number_values!(Number);
+}
+
+#[cfg(feature = "toml")]
+mod toml {
+    use super::*;
+    use ::toml::value::{Datetime, Table, Value};
+
+    impl_value_types! {
+        Value {
+            Value::String(v) => v,
+            Value::Integer(v) => v,
+            Value::Float(v) => v,
+            Value::Boolean(v) => v,
+            Value::Datetime(v) => v,
+            Value::Array(v) => v,
+            Value::Table(v) => v,
+        }
+    }
+
+    impl Content for Table {
+        impl_map_methods!();
+    }
+
+    impl Content for Datetime {
+        #[inline]
+        fn capacity_hint(&self, _tpl: &Template) -> usize {
+            25
+        }
+
+        #[inline]
+        fn render_escaped<E: Encoder>(&self, encoder: &mut E) -> Result<(), E::Error> {
+            // Nothing to escape here
+            encoder.format_unescaped(self)
+        }
+    }
+}
@@ -223,3 +223,80 @@ fn xe_parse_handlebars(b: &mut Bencher) {
         black_box(handlebars.register_template_string("t1", SOURCE).unwrap());
     });
 }
+
+static FEW_SPECIALS: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
+    eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis \
+    nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute \
+    irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla \
+    pariatur & excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia \
+    deserunt mollit anim id est laborum.";
+
+static MANY_SPECIALS: &str = "<ul class=\"posts\"><li><a href=\"/?page=1&amp;sort=date\">\
+    &lt;First&gt;</a></li><li><a href=\"/?page=2&amp;sort=date\">\"Second\" & more</a>\
+    </li><li><a href=\"/?page=3&amp;sort=date\">&lt;Third&gt;</a></li></ul><p>1 < 2 > 0</p>";
+
+/// The byte by byte loop the encoders used before, kept for comparison.
+fn escape_naive(buf: &mut String, part: &str) {
+    let mut start = 0;
+
+    for (idx, byte) in part.bytes().enumerate() {
+        let replace = match byte {
+            b'<' => "&lt;",
+            b'>' => "&gt;",
+            b'&' => "&amp;",
+            b'"' => "&quot;",
+            _ => continue,
+        };
+
+        buf.push_str(&part[start..idx]);
+        buf.push_str(replace);
+
+        start = idx + 1;
+    }
+
+    buf.push_str(&part[start..]);
+}
+
+fn bench_escape(b: &mut Bencher, part: &str) {
+    use ramhorns::encoding::Encoder;
+
+    let mut buf = String::with_capacity(part.len() * 2);
+    b.bytes = part.len() as u64;
+
+    b.iter(|| {
+        buf.clear();
+        let _ = buf.write_escaped(black_box(part));
+        black_box(&buf);
+    });
+}
+
+fn bench_escape_naive(b: &mut Bencher, part: &str) {
+    let mut buf = String::with_capacity(part.len() * 2);
+    b.bytes = part.len() as u64;
+
+    b.iter(|| {
+        buf.clear();
+        escape_naive(&mut buf, black_box(part));
+        black_box(&buf);
+    });
+}
+
+#[bench]
+fn ya_escape_few_specials(b: &mut Bencher) {
+    bench_escape(b, FEW_SPECIALS);
+}
+
+#[bench]
+fn yb_escape_few_specials_naive(b: &mut Bencher) {
+    bench_escape_naive(b, FEW_SPECIALS);
+}
+
+#[bench]
+fn yc_escape_many_specials(b: &mut Bencher) {
+    bench_escape(b, MANY_SPECIALS);
+}
+
+#[bench]
+fn yd_escape_many_specials_naive(b: &mut Bencher) {
+    bench_escape_naive(b, MANY_SPECIALS);
+}
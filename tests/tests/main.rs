@@ -475,6 +475,427 @@ fn can_render_markdown() {
     assert_eq!(html, "<h1>This is *the* title</h1><div><p>This is <em>the</em> <strong>body</strong>!</p>\n</div>");
 }
 
+#[test]
+// Expects the HTML as rendered by pulldown-cmark
+#[cfg(not(feature = "comrak"))]
+fn can_render_markdown_with_options() {
+    use ramhorns::encoding::{BudgetEncoder, BudgetError, EscapeTable, RenderBudget};
+    use ramhorns::{Escape, MarkdownOptions};
+
+    #[derive(Content)]
+    struct Doc<'a> {
+        #[ramhorns(md(tables, strikethrough, tasklists))]
+        body: &'a str,
+        #[ramhorns(md)]
+        plain: &'a str,
+        items: Vec<Item<'a>>,
+    }
+
+    #[derive(Content)]
+    struct Item<'a> {
+        name: &'a str,
+        price: u32,
+    }
+
+    let doc = Doc {
+        body: "| A | B |\n|---|---|\n| ~~1~~ | 2 |\n\n- [x] done",
+        plain: "~~1~~",
+        items: vec![
+            Item {
+                name: "Fish & Chips",
+                price: 10,
+            },
+            Item {
+                name: "Tea",
+                price: 2,
+            },
+        ],
+    };
+
+    let tpl = Template::new("{{body}}{{plain}}").unwrap();
+
+    assert_eq!(
+        tpl.render(&doc),
+        "<table><thead><tr><th>A</th><th>B</th></tr></thead><tbody>\n\
+         <tr><td><del>1</del></td><td>2</td></tr>\n</tbody></table>\n\
+         <ul>\n<li><input disabled=\"\" type=\"checkbox\" checked=\"\"/>\ndone</li>\n</ul>\n\
+         <p>~~1~~</p>\n"
+    );
+
+    let source = "<div>{{#md}}\n| Item | Price |\n|---|---|\n{{#items}}| *{{name}}* | {{price}} |\n{{/items}}{{/md}}</div>";
+    let tpl = Template::new(source).unwrap();
+
+    // Unless enabled, `{{#md}}` is an ordinary section
+    assert_eq!(tpl.render(&doc), "<div></div>");
+
+    let tpl = tpl.with_markdown(MarkdownOptions::new());
+
+    assert_eq!(
+        tpl.render(&doc),
+        "<div><p>| Item | Price |\n|---|---|\n| <em>Fish &amp; Chips</em> | 10 |\n| <em>Tea</em> | 2 |</p>\n</div>"
+    );
+
+    let tpl = tpl.with_markdown(MarkdownOptions::new().tables());
+
+    assert_eq!(
+        tpl.render(&doc),
+        "<div><table><thead><tr><th>Item</th><th>Price</th></tr></thead><tbody>\n\
+         <tr><td><em>Fish &amp; Chips</em></td><td>10</td></tr>\n\
+         <tr><td><em>Tea</em></td><td>2</td></tr>\n</tbody></table>\n</div>"
+    );
+
+    // Budgets apply to the contents of `{{#md}}` sections too
+    let mut encoder = BudgetEncoder::new(String::new(), RenderBudget::new().iterations(1));
+    assert!(matches!(
+        tpl.render_with_encoder(&mut encoder, &doc),
+        Err(BudgetError::Iterations { limit: 1 })
+    ));
+
+    // Values inside of `{{#md}}` sections are escaped like the rest of the template
+    static TABLE: EscapeTable = EscapeTable::HTML.with(b'*', "&#42;");
+
+    #[derive(Content)]
+    struct Note {
+        text: &'static str,
+    }
+
+    let note = Note {
+        text: "*Fish* & Chips",
+    };
+    let tpl = Template::new("{{#md}}{{text}}{{/md}}").unwrap();
+
+    #[derive(Content)]
+    struct Page {
+        md: Note,
+    }
+
+    let page = Page {
+        md: Note { text: "*Fish*" },
+    };
+    assert_eq!(tpl.render(&page), "*Fish*");
+
+    let tpl = tpl.with_markdown(MarkdownOptions::new());
+
+    assert_eq!(tpl.render(&note), "<p><em>Fish</em> &amp; Chips</p>\n");
+
+    let tpl = tpl.with_escape(Escape::Table(&TABLE));
+
+    assert_eq!(tpl.render(&note), "<p>*Fish* &amp; Chips</p>\n");
+}
+
+#[test]
+fn can_render_markdown_with_custom_encoder() {
+    use ramhorns::encoding::Encoder;
+    use std::fmt::{self, Write};
+
+    #[derive(Default)]
+    struct Prose {
+        buf: String,
+    }
+
+    impl Encoder for Prose {
+        type Error = fmt::Error;
+
+        fn write_unescaped(&mut self, part: &str) -> fmt::Result {
+            self.buf.write_str(part)
+        }
+
+        fn write_escaped(&mut self, part: &str) -> fmt::Result {
+            self.buf.write_str(&part.replace('<', "&lt;"))
+        }
+
+        fn write_html(&mut self, html: &str) -> fmt::Result {
+            write!(self.buf, "<div class=\"prose\">{}</div>", html.trim_end())
+        }
+
+        fn format_unescaped<D: fmt::Display>(&mut self, display: D) -> fmt::Result {
+            write!(self.buf, "{}", display)
+        }
+
+        fn format_escaped<D: fmt::Display>(&mut self, display: D) -> fmt::Result {
+            self.write_escaped(&display.to_string())
+        }
+    }
+
+    #[derive(Content)]
+    struct Post<'a> {
+        title: &'a str,
+        #[ramhorns(md)]
+        body: &'a str,
+    }
+
+    let tpl = Template::new("<h1>{{title}}</h1>{{body}}").unwrap();
+    let mut encoder = Prose::default();
+
+    tpl.render_with_encoder(
+        &mut encoder,
+        &Post {
+            title: "<Hi>",
+            body: "*Hello*",
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        encoder.buf,
+        "<h1>&lt;Hi></h1><div class=\"prose\"><p><em>Hello</em></p></div>"
+    );
+}
+
+#[test]
+fn can_render_markdown_with_backend() {
+    use ramhorns::encoding::encode_markdown;
+    use ramhorns::{MarkdownBackend, MarkdownOptions, PulldownCmark};
+
+    struct Shouting;
+
+    impl MarkdownBackend for Shouting {
+        fn render(source: &str, options: MarkdownOptions, html: &mut String) {
+            PulldownCmark::render(&source.to_uppercase(), options, html);
+        }
+    }
+
+    let mut html = String::new();
+    encode_markdown::<Shouting, _>("*hi* ~~there~~", &mut html, MarkdownOptions::new()).unwrap();
+
+    assert_eq!(html, "<p><em>HI</em> ~~THERE~~</p>\n");
+
+    let mut html = String::new();
+    let options = MarkdownOptions::new().strikethrough();
+    encode_markdown::<PulldownCmark, _>("*hi* ~~there~~", &mut html, options).unwrap();
+
+    assert_eq!(html, "<p><em>hi</em> <del>there</del></p>\n");
+}
+
+#[test]
+#[cfg(feature = "comrak")]
+fn can_render_markdown_with_comrak() {
+    use ramhorns::encoding::encode_markdown;
+    use ramhorns::{Comrak, MarkdownOptions};
+
+    #[derive(Content)]
+    struct Readme<'a> {
+        #[ramhorns(md(autolinks, tables))]
+        body: &'a str,
+        #[ramhorns(md(sanitize))]
+        comment: &'a str,
+    }
+
+    let tpl = Template::new("{{body}}{{comment}}").unwrap();
+    let readme = Readme {
+        body: "See www.example.com\n\n<style>p {}</style>",
+        comment: "<b>Hi</b> <img src=x onerror=alert(1)> [me](javascript:alert(1))",
+    };
+
+    assert_eq!(
+        tpl.render(&readme),
+        "<p>See <a href=\"http://www.example.com\">www.example.com</a></p>\n\
+         &lt;style>p {}&lt;/style>\n\
+         <p><b>Hi</b> &lt;img src=x onerror=alert(1)&gt; <a href=\"about:invalid\">me</a></p>\n"
+    );
+
+    let mut html = String::new();
+    encode_markdown::<Comrak, _>(
+        "| A |\n|---|\n| 1 |",
+        &mut html,
+        MarkdownOptions::new().tables(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        html,
+        "<table>\n<thead>\n<tr>\n<th>A</th>\n</tr>\n</thead>\n<tbody>\n<tr>\n<td>1</td>\n</tr>\n</tbody>\n</table>\n"
+    );
+}
+
+#[test]
+// Expects the HTML as rendered by pulldown-cmark
+#[cfg(not(feature = "comrak"))]
+fn can_render_sanitized_markdown() {
+    #[derive(Content)]
+    struct Page<'a> {
+        #[ramhorns(md)]
+        docs: &'a str,
+        #[ramhorns(md(tables, sanitize))]
+        comment: &'a str,
+    }
+
+    let source = "<script>track()</script>\n\n*Hi* <i onmouseover=\"x()\">there</i>, \
+                  [click](JavaScript:alert(1)) ![img](data:image/png;base64,AAAA) \
+                  [home](/home) <javascript:alert(2)>";
+    let page = Page {
+        docs: source,
+        comment: source,
+    };
+
+    let tpl = Template::new("{{docs}}").unwrap();
+
+    assert_eq!(
+        tpl.render(&page),
+        "<script>track()</script>\n\
+         <p><em>Hi</em> <i onmouseover=\"x()\">there</i>, \
+         <a href=\"JavaScript:alert(1)\">click</a> <img src=\"data:image/png;base64,AAAA\" alt=\"img\" /> \
+         <a href=\"/home\">home</a> <a href=\"javascript:alert(2)\">javascript:alert(2)</a></p>\n"
+    );
+
+    let tpl = Template::new("{{comment}}").unwrap();
+
+    assert_eq!(
+        tpl.render(&page),
+        "&lt;script&gt;track()&lt;/script&gt;\n\
+         <p><em>Hi</em> &lt;i onmouseover=&quot;x()&quot;&gt;there</i>, \
+         <a href=\"about:invalid\">click</a> <img src=\"about:invalid\" alt=\"img\" /> \
+         <a href=\"/home\">home</a> <a href=\"about:invalid\">javascript:alert(2)</a></p>\n"
+    );
+}
+
+#[test]
+fn can_render_markdown_document() {
+    use ramhorns::{Heading, MarkdownDocument, MarkdownOptions};
+
+    #[derive(Content)]
+    struct Page {
+        doc: MarkdownDocument,
+    }
+
+    let source = "# Intro\n\n## Setup `cargo`\n\n## Setup `cargo`\n\n## Own id\n\n\
+                  ### Custom {#own-id}\n\n### Again {#own-id}\n\n## Ünïcode stuff!\n\n## *";
+    let doc = MarkdownDocument::with_options(source, MarkdownOptions::new().heading_attributes());
+
+    assert_eq!(
+        doc.toc(),
+        &[
+            Heading {
+                level: 1,
+                text: "Intro".into(),
+                slug: "intro".into(),
+            },
+            Heading {
+                level: 2,
+                text: "Setup cargo".into(),
+                slug: "setup-cargo".into(),
+            },
+            Heading {
+                level: 2,
+                text: "Setup cargo".into(),
+                slug: "setup-cargo-1".into(),
+            },
+            Heading {
+                level: 2,
+                text: "Own id".into(),
+                slug: "own-id-1".into(),
+            },
+            Heading {
+                level: 3,
+                text: "Custom".into(),
+                slug: "own-id".into(),
+            },
+            Heading {
+                level: 3,
+                text: "Again".into(),
+                slug: "own-id-2".into(),
+            },
+            Heading {
+                level: 2,
+                text: "Ünïcode stuff!".into(),
+                slug: "ünïcode-stuff".into(),
+            },
+            Heading {
+                level: 2,
+                text: "*".into(),
+                slug: "section-1".into(),
+            },
+        ]
+    );
+    assert_eq!(
+        doc.html(),
+        "<h1 id=\"intro\">Intro</h1>\n\
+         <h2 id=\"setup-cargo\">Setup <code>cargo</code></h2>\n\
+         <h2 id=\"setup-cargo-1\">Setup <code>cargo</code></h2>\n\
+         <h2 id=\"own-id-1\">Own id</h2>\n\
+         <h3 id=\"own-id\">Custom</h3>\n\
+         <h3 id=\"own-id-2\">Again</h3>\n\
+         <h2 id=\"ünïcode-stuff\">Ünïcode stuff!</h2>\n\
+         <h2 id=\"section-1\">*</h2>\n"
+    );
+
+    let tpl = Template::new(
+        "{{#doc}}{{#toc}}<a class=\"h{{level}}\" href=\"#{{slug}}\">{{text}}</a>{{/toc}}\
+         {{^toc}}No headings{{/toc}}|{{doc}}{{/doc}}",
+    )
+    .unwrap();
+
+    let page = Page {
+        doc: MarkdownDocument::new("Just *text*"),
+    };
+    assert_eq!(tpl.render(&page), "No headings|<p>Just <em>text</em></p>\n");
+
+    let page = Page {
+        doc: MarkdownDocument::with_options(
+            "# <script>x</script>Title",
+            MarkdownOptions::new().sanitize(),
+        ),
+    };
+    assert_eq!(
+        tpl.render(&page),
+        "<a class=\"h1\" href=\"#xtitle\">xTitle</a>|\
+         <h1 id=\"xtitle\">&lt;script&gt;x&lt;/script&gt;Title</h1>\n"
+    );
+}
+
+#[test]
+fn can_render_highlighted_markdown() {
+    use ramhorns::{Highlight, MarkdownDocument, MarkdownOptions};
+
+    #[derive(Content)]
+    struct Post<'a> {
+        #[ramhorns(md(highlight))]
+        body: &'a str,
+    }
+
+    let source = "```rust\nfn main() {}\n```\n\n```nonexistent\n<b>\n```\n\n```\nplain\n```";
+    let tpl = Template::new("{{body}}").unwrap();
+
+    let html = tpl.render(&Post { body: source });
+    let (rust, rest) = html.split_at(html.find("</pre>").unwrap() + 7);
+
+    assert!(rust.starts_with("<pre><code class=\"language-rust\"><span class=\"source rust\">"));
+    assert!(rust.contains("<span class=\"storage type function rust\">fn</span>"));
+    assert!(rust.contains("<span class=\"entity name function rust\">main</span>"));
+    assert!(rust.ends_with("</span></code></pre>\n"));
+
+    // Unknown languages and code without a language are left alone
+    assert_eq!(
+        rest,
+        "<pre><code class=\"language-nonexistent\">&lt;b&gt;\n</code></pre>\n\
+         <pre><code>plain\n</code></pre>\n"
+    );
+
+    let options = MarkdownOptions::new()
+        .sanitize()
+        .highlight_with(Highlight::Theme("InspiredGitHub"));
+    let doc = MarkdownDocument::with_options("```js\nalert('<b>')\n```", options);
+
+    assert!(doc
+        .html()
+        .starts_with("<pre style=\"background-color:#ffffff;\">\n<span style=\""));
+    assert!(doc.html().contains("&lt;b&gt;"));
+    assert!(!doc.html().contains("<b>"));
+
+    let options = MarkdownOptions::new().highlight_with(Highlight::Theme("No such theme"));
+    let doc = MarkdownDocument::with_options("```js\nalert('<b>')\n```", options);
+
+    assert_eq!(
+        doc.html(),
+        "<pre><code class=\"language-js\">alert('&lt;b&gt;')\n</code></pre>\n"
+    );
+
+    assert!(Highlight::css("InspiredGitHub")
+        .unwrap()
+        .contains(".comment {"));
+    assert_eq!(Highlight::css("No such theme"), None);
+}
+
 #[test]
 fn can_render_callback() {
     fn double<E>(s: &str, enc: &mut E) -> Result<(), E::Error>
@@ -802,86 +1223,412 @@ fn simple_partials_extend() {
 }
 
 #[test]
-fn illegal_partials() {
-    use ramhorns::Error;
+fn extensionless_partials() {
+    let mut tpls: Ramhorns = Ramhorns::lazy("templates").unwrap().extensionless("html");
+    tpls.extend_from_folder("templates").unwrap();
 
-    let mut tpls: Ramhorns = Ramhorns::lazy("templates").unwrap();
+    let post = Post {
+        title: "Hello, Ramhorns!",
+        body: "This is a really simple test of the rendering!",
+    };
 
-    let tpl1 = Template::new("<div>{{>templates/layout.html}}</div>");
-    let tpl2 = tpls.from_file("illegal.hehe");
+    assert!(tpls.names().any(|name| name == "includes/article.html"));
+    assert!(tpls.names().all(|name| name.ends_with(".html")));
+    assert_eq!(
+        tpls.get("includes/article").unwrap().render(&post),
+        tpls.get("includes/article.html").unwrap().render(&post),
+    );
 
-    if let Err(Error::PartialsDisabled) = tpl1 {
-    } else {
-        panic!("Partials loaded while parsing from &str");
-    }
+    tpls.insert("<div>{{>includes/article}}</div>", "wrapped.html")
+        .unwrap();
 
-    if let Err(Error::IllegalPartial(name)) = tpl2 {
-        assert_eq!(name, "../Cargo.toml".into());
-    } else {
-        panic!("Partials loaded out of the allowed directory");
-    }
+    assert_eq!(
+        tpls.get("wrapped").unwrap().render(&post),
+        "<div><article>This is a really simple test of the rendering!</article></div>"
+    );
+
+    tpls.remove("wrapped");
+
+    assert!(!tpls.contains("wrapped.html"));
+    assert!(!tpls.contains("wrapped"));
+
+    let mut tpls: Ramhorns = Ramhorns::lazy("templates").unwrap().extensionless("html");
+
+    assert_eq!(
+        tpls.from_file("layout").unwrap().render(&""),
+        "<head><h1>Head</h1></head>"
+    );
+    assert!(tpls.contains("layout.html"));
+    assert!(tpls.contains("test"));
 }
 
-#[cfg(feature = "indexes")]
 #[test]
-fn section_index() {
-    let source = "{{#people}}{{#-0}}{{#-last}}{{{name}}}{{/-last}}{{/-0}}{{/people}}";
-    let tpl = Template::new(source).unwrap();
+fn manage_partials() {
+    use ramhorns::Error;
 
-    #[derive(Content)]
-    struct Person {
-        name: String,
-    }
+    let mut tpls: Ramhorns = Ramhorns::lazy("templates").unwrap();
 
-    #[derive(Content)]
-    struct Api {
-        people: Vec<Vec<Person>>,
-    }
+    assert!(tpls.is_empty());
 
-    let render = tpl.render(&Api {
-        people: vec![
-            vec![
-                Person {
-                    name: "first".to_string(),
-                },
-                Person {
-                    name: "last".to_string(),
-                },
-            ],
-            vec![
-                Person {
-                    name: "second first".to_string(),
-                },
-                Person {
-                    name: "second last".to_string(),
-                },
-            ],
-        ],
-    });
+    tpls.from_file("layout.html").unwrap();
 
-    assert_eq!(render, "last");
-}
+    let mut names: Vec<_> = tpls.names().collect();
+    names.sort_unstable();
 
-#[cfg(feature = "indexes")]
-#[test]
-fn section_index_exclude() {
-    let source =
-        "People:\n{{#people}}name: {{{name}}}|{{^-last}}{{#-0}}(Index 0){{/-0}}{{^-0}}(Not Index 0){{/-0}},\n{{/-last}}{{#-last}}(Index Last){{/-last}}{{/people}}";
-    let tpl = Template::new(source).unwrap();
+    assert_eq!(names, ["layout.html", "test.html"]);
+    assert_eq!(tpls.len(), 2);
+    assert!(tpls.contains("test.html"));
 
-    #[derive(Content)]
-    struct Person {
-        name: String,
-    }
+    let old = tpls.replace("<h2>New</h2>", "test.html").unwrap().unwrap();
 
-    #[derive(Content)]
-    struct Api {
-        people: Vec<Person>,
-    }
+    assert_eq!(old.render(&""), "<h1>Head</h1>");
+    assert!(tpls.replace("Fresh", "fresh.html").unwrap().is_none());
+    assert_eq!(
+        tpls.get("layout.html").unwrap().render(&""),
+        "<head><h1>Head</h1></head>"
+    );
 
-    let render = tpl.render(&Api {
-        people: vec![
-            Person {
+    tpls.rename("fresh.html", "renamed.html").unwrap();
+
+    assert!(!tpls.contains("fresh.html"));
+    assert_eq!(tpls.get("renamed.html").unwrap().render(&""), "Fresh");
+
+    tpls.rename("renamed.html", "./nested/renamed.html")
+        .unwrap();
+
+    assert!(tpls.names().any(|name| name == "nested/renamed.html"));
+    assert_eq!(
+        tpls.get("nested/renamed.html").unwrap().render(&""),
+        "Fresh"
+    );
+
+    tpls.rename("nested/renamed.html", "renamed.html").unwrap();
+
+    match tpls.rename("fresh.html", "other.html") {
+        Err(Error::NotFound(name)) => assert_eq!(&*name, "fresh.html"),
+        _ => panic!("Renamed a missing template"),
+    }
+
+    let removed = tpls.remove("test.html").unwrap();
+
+    assert_eq!(removed.render(&""), "<h2>New</h2>");
+    assert!(tpls.remove("test.html").is_none());
+
+    tpls.retain(|name, _| name.starts_with("layout"));
+
+    assert_eq!(
+        tpls.iter()
+            .map(|(name, tpl)| (name, tpl.render(&"")))
+            .collect::<Vec<_>>(),
+        [("layout.html", "<head><h1>Head</h1></head>".to_string())]
+    );
+}
+
+#[test]
+fn shared_partials() {
+    use ramhorns::SharedRamhorns;
+    use std::sync::Arc;
+
+    let tpls: Arc<SharedRamhorns> = Arc::new(SharedRamhorns::lazy("templates").unwrap());
+
+    assert!(tpls.get_loaded("layout.html").is_none());
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let tpls = tpls.clone();
+            std::thread::spawn(move || tpls.get("layout.html").unwrap().render(&""))
+        })
+        .collect();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), "<head><h1>Head</h1></head>");
+    }
+
+    assert!(tpls.get_loaded("layout.html").is_some());
+    assert!(tpls.get_loaded("test.html").is_some());
+}
+
+#[test]
+fn shared_reload_keeps_handles_valid() {
+    use ramhorns::SharedRamhorns;
+    use std::sync::Arc;
+
+    let tpls: SharedRamhorns = SharedRamhorns::lazy("templates").unwrap();
+
+    let before = tpls.get("layout.html").unwrap();
+    tpls.insert("<h2>Replaced</h2>", "test.html").unwrap();
+    let after = tpls.reload("layout.html").unwrap();
+
+    assert!(!Arc::ptr_eq(&before, &after));
+    assert_eq!(before.render(&""), "<head><h1>Head</h1></head>");
+    assert_eq!(after.render(&""), "<head><h2>Replaced</h2></head>");
+    assert!(Arc::ptr_eq(&after, &tpls.get("layout.html").unwrap()));
+}
+
+#[test]
+fn folder_options() {
+    use ramhorns::{FolderOptions, Symlinks};
+
+    let options = FolderOptions::new()
+        .extension("html")
+        .extension("mustache")
+        .exclude("_wip/**")
+        .exclude("**/node_modules")
+        .skip_hidden(true);
+
+    let tpls: Ramhorns =
+        Ramhorns::from_folder_with_options("filtered_templates", &options).unwrap();
+    let mut names: Vec<_> = tpls.names().collect();
+    names.sort_unstable();
+
+    assert_eq!(
+        names,
+        ["linked.html", "page.html", "shared/footer.mustache"]
+    );
+    assert_eq!(
+        tpls.get("page.html").unwrap().render(&Post {
+            title: "Hello",
+            body: "",
+        }),
+        "<p>Hello</p><footer>Footer</footer>"
+    );
+
+    for symlinks in [Symlinks::FollowInside, Symlinks::Skip] {
+        let tpls: Ramhorns = Ramhorns::from_folder_with_options(
+            "filtered_templates",
+            &options.clone().symlinks(symlinks),
+        )
+        .unwrap();
+
+        assert!(!tpls.contains("linked.html"));
+    }
+
+    let tpls: Ramhorns = Ramhorns::from_folder_with_options(
+        "filtered_templates",
+        &options.clone().include("shared/*"),
+    )
+    .unwrap();
+
+    assert_eq!(tpls.names().collect::<Vec<_>>(), ["shared/footer.mustache"]);
+
+    // Dangling links are skipped whatever the policy, collecting errors or not
+    for symlinks in [Symlinks::Follow, Symlinks::FollowInside, Symlinks::Skip] {
+        for collect in [false, true] {
+            let options = FolderOptions::new()
+                .symlinks(symlinks)
+                .collect_errors(collect);
+            let tpls: Ramhorns =
+                Ramhorns::from_folder_with_options("dangling_templates", &options).unwrap();
+
+            assert_eq!(tpls.names().collect::<Vec<_>>(), ["ok.html"]);
+        }
+    }
+
+    // Links back to an ancestor folder are walked only once
+    let tpls: Ramhorns = Ramhorns::from_folder("cyclic_templates").unwrap();
+
+    assert_eq!(tpls.names().collect::<Vec<_>>(), ["page.html"]);
+
+    // Without the options, broken drafts are loaded as well
+    let unfiltered: Result<Ramhorns, _> = Ramhorns::from_folder("filtered_templates");
+
+    assert!(unfiltered.is_err());
+}
+
+#[test]
+fn render_in_layout() {
+    use ramhorns::Error;
+
+    let mut tpls: Ramhorns = Ramhorns::from_folder("layout_templates").unwrap();
+    let post = Post {
+        title: "Hello, Ramhorns!",
+        body: "<3",
+    };
+
+    assert_eq!(
+        tpls.render_in_layout("page.html", "layout.html", &post)
+            .unwrap(),
+        "<title>Hello, Ramhorns!</title><main><h1>Hello, Ramhorns!</h1></main>"
+    );
+    assert!(matches!(
+        tpls.render_in_layout("page.html", "nope.html", &post),
+        Err(Error::NotFound(_))
+    ));
+
+    assert_eq!(
+        tpls.render("page.html", &post).unwrap(),
+        "<h1>Hello, Ramhorns!</h1>"
+    );
+
+    tpls.set_layout("", "layout.html");
+    tpls.set_layout("blog/", "blog/layout.html");
+
+    assert_eq!(tpls.layout_for("blog/post.html"), Some("blog/layout.html"));
+    assert_eq!(
+        tpls.render("page.html", &post).unwrap(),
+        "<title>Hello, Ramhorns!</title><main><h1>Hello, Ramhorns!</h1></main>"
+    );
+    assert_eq!(
+        tpls.render("blog/post.html", &post).unwrap(),
+        "<title>Blog: Hello, Ramhorns!</title><article>&lt;3</article>"
+    );
+    assert_eq!(
+        tpls.render("layout.html", &post).unwrap(),
+        "<title>Hello, Ramhorns!</title><main></main>"
+    );
+}
+
+#[test]
+fn illegal_partials() {
+    use ramhorns::Error;
+
+    let mut tpls: Ramhorns = Ramhorns::lazy("templates").unwrap();
+
+    let tpl1 = Template::new("<div>{{>templates/layout.html}}</div>");
+    let tpl2 = tpls.from_file("illegal.hehe");
+
+    if let Err(Error::PartialsDisabled) = tpl1 {
+    } else {
+        panic!("Partials loaded while parsing from &str");
+    }
+
+    if let Err(Error::InTemplate { name, source, .. }) = tpl2 {
+        assert_eq!(&*name, "illegal.hehe");
+        assert!(matches!(*source, Error::IllegalPartial(name) if &*name == "../Cargo.toml"));
+    } else {
+        panic!("Partials loaded out of the allowed directory");
+    }
+}
+
+#[test]
+fn errors_name_templates() {
+    use ramhorns::Error;
+    use std::io::ErrorKind;
+
+    let mut tpls: Ramhorns = Ramhorns::lazy("broken_templates").unwrap();
+
+    match tpls.from_file("page.html") {
+        Err(Error::InTemplate {
+            name,
+            include_chain,
+            source,
+        }) => {
+            assert_eq!(&*name, "broken.html");
+            assert_eq!(include_chain, ["page.html".into(), "layout.html".into()]);
+            assert!(matches!(*source, Error::UnclosedTag));
+        }
+        _ => panic!("Expected an error naming the broken template"),
+    }
+
+    match tpls.from_file("missing.html") {
+        Err(Error::InTemplate { name, source, .. }) => {
+            assert_eq!(&*name, "missing.html");
+            assert!(matches!(*source, Error::Io(err) if err.kind() == ErrorKind::NotFound));
+        }
+        _ => panic!("Expected an error naming the missing partial"),
+    }
+}
+
+#[test]
+fn collect_folder_errors() {
+    use ramhorns::{Error, FolderOptions};
+
+    let mut tpls: Ramhorns = Ramhorns::lazy("broken_templates").unwrap();
+    let options = FolderOptions::new().collect_errors(true);
+
+    match tpls.extend_from_folder_with_options("broken_templates", &options) {
+        Err(Error::Multiple(errors)) => {
+            let mut names: Vec<_> = errors
+                .iter()
+                .map(|error| match error {
+                    Error::InTemplate { name, .. } => name.to_string(),
+                    _ => panic!("Expected an error naming the template"),
+                })
+                .collect();
+            names.sort_unstable();
+            names.dedup();
+
+            assert_eq!(names, ["broken.html", "missing.html"]);
+        }
+        _ => panic!("Expected errors for all broken templates"),
+    }
+
+    assert_eq!(tpls.names().collect::<Vec<_>>(), ["ok.html"]);
+
+    let (tpls, errors): (Ramhorns, _) =
+        Ramhorns::from_folder_with_errors("broken_templates", &FolderOptions::new()).unwrap();
+
+    assert_eq!(tpls.names().collect::<Vec<_>>(), ["ok.html"]);
+    assert!(errors
+        .iter()
+        .all(|error| matches!(error, Error::InTemplate { .. })));
+    assert!(!errors.is_empty());
+
+    let first: Result<Ramhorns, _> = Ramhorns::from_folder("broken_templates");
+
+    assert!(matches!(first, Err(Error::InTemplate { .. })));
+}
+
+#[cfg(feature = "indexes")]
+#[test]
+fn section_index() {
+    let source = "{{#people}}{{#-0}}{{#-last}}{{{name}}}{{/-last}}{{/-0}}{{/people}}";
+    let tpl = Template::new(source).unwrap();
+
+    #[derive(Content)]
+    struct Person {
+        name: String,
+    }
+
+    #[derive(Content)]
+    struct Api {
+        people: Vec<Vec<Person>>,
+    }
+
+    let render = tpl.render(&Api {
+        people: vec![
+            vec![
+                Person {
+                    name: "first".to_string(),
+                },
+                Person {
+                    name: "last".to_string(),
+                },
+            ],
+            vec![
+                Person {
+                    name: "second first".to_string(),
+                },
+                Person {
+                    name: "second last".to_string(),
+                },
+            ],
+        ],
+    });
+
+    assert_eq!(render, "last");
+}
+
+#[cfg(feature = "indexes")]
+#[test]
+fn section_index_exclude() {
+    let source =
+        "People:\n{{#people}}name: {{{name}}}|{{^-last}}{{#-0}}(Index 0){{/-0}}{{^-0}}(Not Index 0){{/-0}},\n{{/-last}}{{#-last}}(Index Last){{/-last}}{{/people}}";
+    let tpl = Template::new(source).unwrap();
+
+    #[derive(Content)]
+    struct Person {
+        name: String,
+    }
+
+    #[derive(Content)]
+    struct Api {
+        people: Vec<Person>,
+    }
+
+    let render = tpl.render(&Api {
+        people: vec![
+            Person {
                 name: "first".to_string(),
             },
             Person {
@@ -898,3 +1645,882 @@ fn section_index_exclude() {
         "People:\nname: first|(Index 0),\nname: second|(Not Index 0),\nname: last|(Index Last)"
     );
 }
+
+#[test]
+fn can_render_serde_values() {
+    use ramhorns::Serde;
+    use serde_derive::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Order {
+        customer: Customer,
+        items: Vec<Item>,
+        notes: Option<String>,
+        status: Status,
+        extras: BTreeMap<String, u32>,
+        gift: (),
+    }
+
+    #[derive(Serialize)]
+    struct Customer {
+        name: &'static str,
+        vip: bool,
+    }
+
+    #[derive(Serialize)]
+    struct Item(&'static str);
+
+    #[derive(Serialize)]
+    struct Line {
+        name: &'static str,
+        price: f32,
+    }
+
+    #[derive(Serialize)]
+    enum Status {
+        Shipped,
+    }
+
+    let order = Order {
+        customer: Customer {
+            name: "<Maciej>",
+            vip: true,
+        },
+        items: vec![Item("Fish"), Item("Chips")],
+        notes: None,
+        status: Status::Shipped,
+        extras: vec![("ketchup".to_string(), 2), ("mayo".to_string(), 0)]
+            .into_iter()
+            .collect(),
+        gift: (),
+    };
+
+    let tpl = Template::new(
+        "{{#customer}}{{name}} {{{name}}}{{#vip}} (VIP){{/vip}}{{/customer}}: {{status}}\n\
+         {{#items}}- item\n{{/items}}\
+         {{^notes}}No notes{{/notes}}{{#gift}}, gift{{/gift}}\n\
+         {{#extras}}ketchup: {{ketchup}}{{#mayo}}, mayo{{/mayo}}{{/extras}}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        tpl.render(&Serde(&order)),
+        "&lt;Maciej&gt; <Maciej> (VIP): Shipped\n- item\n- item\nNo notes\nketchup: 2"
+    );
+
+    let lines = vec![
+        Line {
+            name: "Fish",
+            price: 10.5,
+        },
+        Line {
+            name: "Chips",
+            price: 2.0,
+        },
+    ];
+    let tpl =
+        Template::new("{{#lines}}{{name}}: {{price}}; {{/lines}}{{^empty}}none{{/empty}}").unwrap();
+    let mut data = BTreeMap::new();
+    data.insert("lines", Serde(&lines));
+
+    assert_eq!(tpl.render(&data), "Fish: 10.5; Chips: 2; none");
+
+    #[cfg(feature = "indexes")]
+    {
+        let tpl = Template::new("{{#lines}}{{name}}{{^-last}}, {{/-last}}{{/lines}}").unwrap();
+
+        assert_eq!(tpl.render(&data), "Fish, Chips");
+    }
+}
+
+#[test]
+fn can_render_json_values() {
+    let data = serde_json::json!({
+        "title": "Fish & Chips",
+        "price": 10.5,
+        "stock": 0,
+        "vegan": false,
+        "notes": null,
+        "tags": [],
+        "items": [
+            { "name": "Fish", "count": 2 },
+            { "name": "Chips", "count": -1 },
+        ],
+    });
+
+    let tpl = Template::new(
+        "<h1>{{title}}</h1>{{{title}}} {{price}}\n\
+         {{^stock}}Sold out{{/stock}}{{^vegan}}, not vegan{{/vegan}}\
+         {{^notes}}, no notes{{/notes}}{{^tags}}, no tags{{/tags}}{{#missing}}!{{/missing}}\n\
+         {{#items}}{{name}}: {{count}} {{title}}; {{/items}}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        tpl.render(&data),
+        "<h1>Fish &amp; Chips</h1>Fish & Chips 10.5\n\
+         Sold out, not vegan, no notes, no tags\n\
+         Fish: 2 Fish &amp; Chips; Chips: -1 Fish &amp; Chips; "
+    );
+
+    let tpl = Template::new("{{title}}").unwrap();
+
+    assert_eq!(tpl.render(data.as_object().unwrap()), "Fish &amp; Chips");
+
+    #[cfg(feature = "indexes")]
+    {
+        let tpl = Template::new("{{#items}}{{name}}{{^-last}}, {{/-last}}{{/items}}").unwrap();
+
+        assert_eq!(tpl.render(&data), "Fish, Chips");
+    }
+}
+
+#[test]
+fn can_render_frontmatter_values() {
+    let tpl = Template::new(
+        "<h1>{{title}}</h1>{{date}} {{#draft}}(draft){{/draft}}{{^views}}unseen{{/views}}\n\
+         {{#author}}{{name}} ({{email}}){{/author}}\n\
+         {{#tags}}tagged {{/tags}}{{#links}}<a href=\"{{url}}\">{{title}}</a>{{/links}}",
+    )
+    .unwrap();
+    let expected = "<h1>Fish &amp; Chips</h1>2024-05-01 unseen\n\
+                    Maciej (hello@maciej.codes)\n\
+                    <a href=\"/fish\">Fish &amp; Chips</a><a href=\"/chips\">Chips</a>";
+
+    let toml: toml::Table = r#"
+        title = "Fish & Chips"
+        date = 2024-05-01
+        draft = false
+        views = 0
+        tags = []
+
+        [author]
+        name = "Maciej"
+        email = "hello@maciej.codes"
+
+        [[links]]
+        url = "/fish"
+
+        [[links]]
+        url = "/chips"
+        title = "Chips"
+    "#
+    .parse()
+    .unwrap();
+
+    assert_eq!(tpl.render(&toml), expected);
+
+    let yaml: serde_norway::Value = serde_norway::from_str(
+        r#"
+        title: Fish & Chips
+        date: 2024-05-01
+        draft: false
+        views: 0
+        tags: []
+        author:
+          name: Maciej
+          email: hello@maciej.codes
+        links:
+          - url: /fish
+          - url: /chips
+            title: !short Chips
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(tpl.render(&yaml), expected);
+}
+
+#[derive(Content)]
+struct Greeting<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn localized_templates() {
+    let mut tpls: Ramhorns = Ramhorns::lazy("locale_templates")
+        .unwrap()
+        .locales(["de", "de-AT"]);
+    tpls.extend_from_folder("locale_templates").unwrap();
+    let greeting = Greeting { name: "Maciej" };
+
+    let render = |locale| {
+        tpls.get_localized("welcome.html", locale)
+            .unwrap()
+            .render(&greeting)
+    };
+
+    assert_eq!(
+        render("de-AT"),
+        "<h1>Hallo</h1><p>Servus, Maciej!</p><footer><nav>Start</nav></footer>"
+    );
+    assert_eq!(
+        render("de-CH"),
+        "<h1>Hallo</h1><p>Willkommen, Maciej!</p><footer><nav>Start</nav></footer>"
+    );
+    assert_eq!(
+        render("de"),
+        "<h1>Hallo</h1><p>Willkommen, Maciej!</p><footer><nav>Start</nav></footer>"
+    );
+    assert_eq!(
+        render("fr-CA"),
+        "<h1>Hello</h1><p>Welcome, Maciej!</p><footer><nav>Home</nav></footer>"
+    );
+    assert_eq!(
+        tpls.get("welcome.html").unwrap().render(&greeting),
+        "<h1>Hello</h1><p>Welcome, Maciej!</p><footer><nav>Home</nav></footer>"
+    );
+    assert_eq!(
+        tpls.get("footer.html").unwrap().render(&greeting),
+        "<footer><nav>Home</nav></footer>"
+    );
+    assert!(tpls.get_localized("nope.html", "de").is_none());
+}
+
+#[test]
+fn localized_templates_lazy() {
+    let mut tpls: Ramhorns = Ramhorns::lazy("locale_templates")
+        .unwrap()
+        .extensionless("html");
+    let greeting = Greeting { name: "Maciej" };
+
+    assert_eq!(
+        tpls.from_file_localized("welcome", "de-CH")
+            .unwrap()
+            .render(&greeting),
+        "<h1>Hallo</h1><p>Willkommen, Maciej!</p><footer><nav>Start</nav></footer>"
+    );
+    assert!(tpls.contains("welcome.de.html"));
+    assert!(tpls.contains("header.de.html"));
+    assert!(!tpls.contains("welcome.html"));
+
+    assert_eq!(
+        tpls.from_file_localized("welcome", "pl")
+            .unwrap()
+            .render(&greeting),
+        "<h1>Hello</h1><p>Welcome, Maciej!</p><footer><nav>Home</nav></footer>"
+    );
+    assert_eq!(
+        tpls.get_localized("welcome", "de-AT")
+            .unwrap()
+            .render(&greeting),
+        "<h1>Hallo</h1><p>Willkommen, Maciej!</p><footer><nav>Start</nav></footer>"
+    );
+}
+
+#[test]
+fn localized_templates_in_memory() {
+    let mut tpls: Ramhorns = Ramhorns::lazy("locale_templates").unwrap().locales(["de"]);
+
+    tpls.insert("<menu>{{>nav.html}}</menu>", "menu.html")
+        .unwrap();
+    tpls.insert("{{>menu.html}}", "page.de.html").unwrap();
+    tpls.insert("{{>menu.html}}", "page.js.html").unwrap();
+
+    assert_eq!(
+        tpls.get("page.de.html").unwrap().render(&""),
+        "<menu><nav>Start</nav></menu>"
+    );
+    assert_eq!(
+        tpls.get("page.js.html").unwrap().render(&""),
+        "<menu><nav>Home</nav></menu>"
+    );
+    assert_eq!(
+        tpls.get("menu.html").unwrap().render(&""),
+        "<menu><nav>Home</nav></menu>"
+    );
+}
+
+#[test]
+fn render_to_dir() {
+    use ramhorns::{OutputOptions, OutputStatus};
+
+    let tpls: Ramhorns = Ramhorns::from_folder("layout_templates").unwrap();
+    let dir = std::env::temp_dir().join(format!("ramhorns-render-to-dir-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let post = Post {
+        title: "Hello, Ramhorns!",
+        body: "<3",
+    };
+    let pages = |name: &str| match name.ends_with("layout.html") {
+        true => None,
+        false => Some(&post),
+    };
+    let statuses = |files: Vec<ramhorns::OutputFile>| {
+        files
+            .into_iter()
+            .map(|file| (file.name, file.status))
+            .collect::<Vec<_>>()
+    };
+
+    let options = OutputOptions::new().dry_run(true);
+    let files = tpls.render_to_dir(&dir, &options, pages).unwrap();
+
+    assert_eq!(files[0].path, dir.join("blog/post.html"));
+    assert_eq!(
+        statuses(files),
+        [
+            ("blog/post.html".to_string(), OutputStatus::Created),
+            ("page.html".to_string(), OutputStatus::Created),
+        ]
+    );
+    assert!(!dir.exists());
+
+    let options = OutputOptions::new();
+    let files = tpls.render_to_dir(&dir, &options, pages).unwrap();
+
+    assert_eq!(files.len(), 2);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("blog/post.html")).unwrap(),
+        "<article>&lt;3</article>"
+    );
+
+    std::fs::write(dir.join("page.html"), "stale").unwrap();
+
+    let files = tpls.render_to_dir(&dir, &options, pages).unwrap();
+
+    assert_eq!(
+        statuses(files),
+        [
+            ("blog/post.html".to_string(), OutputStatus::Unchanged),
+            ("page.html".to_string(), OutputStatus::Updated),
+        ]
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("page.html")).unwrap(),
+        "<h1>Hello, Ramhorns!</h1>"
+    );
+
+    let options = OutputOptions::new().include("blog/**");
+    let files = tpls.render_to_dir(&dir, &options, pages).unwrap();
+
+    assert_eq!(
+        statuses(files),
+        [("blog/post.html".to_string(), OutputStatus::Unchanged)]
+    );
+
+    let mut tpls = tpls;
+    tpls.set_layout("blog", "blog/layout.html");
+
+    let files = tpls.render_to_dir(&dir, &options, pages).unwrap();
+
+    assert_eq!(
+        statuses(files),
+        [("blog/post.html".to_string(), OutputStatus::Updated)]
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("blog/post.html")).unwrap(),
+        "<title>Blog: Hello, Ramhorns!</title><article>&lt;3</article>"
+    );
+
+    tpls.insert("<p>{{title}}</p>", "../escaped.html").unwrap();
+
+    assert!(tpls
+        .render_to_dir(&dir, &OutputOptions::new(), pages)
+        .is_err());
+    assert!(!dir.join("../escaped.html").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn context_aware_escaping() {
+    #[derive(Content)]
+    struct Link<'a> {
+        url: &'a str,
+        title: &'a str,
+    }
+
+    let tpl = Template::new(concat!(
+        "<a href=\"{{url}}\" title='{{title}}' data-title={{title}}>{{title}}</a>",
+        "<script>var title = \"{{title}}\";</script>",
+        "<p style=\"content: '{{title}}'\" onclick=\"alert('{{title}}')\">{{{title}}}</p>",
+    ))
+    .unwrap();
+
+    let rendered = tpl.render(&Link {
+        url: "javascript:alert(1)",
+        title: "It's <b>\"bold\"</b>",
+    });
+
+    assert_eq!(
+        rendered,
+        concat!(
+            "<a href=\"about:invalid\" title='It&#x27;s &lt;b&gt;&quot;bold&quot;&lt;/b&gt;' ",
+            "data-title=It&#x27;s&#x20;&#x3C;b&#x3E;&#x22;bold&#x22;&#x3C;&#x2F;b&#x3E;>",
+            "It's &lt;b&gt;&quot;bold&quot;&lt;/b&gt;</a>",
+            "<script>var title = \"It\\x27s\\x20\\x3Cb\\x3E\\x22bold\\x22\\x3C\\x2Fb\\x3E\";</script>",
+            "<p style=\"content: 'It\\27 s\\20 \\3C b\\3E \\22 bold\\22 \\3C \\2F b\\3E '\" ",
+            "onclick=\"alert('It\\x27s\\x20\\x3Cb\\x3E\\x22bold\\x22\\x3C\\x2Fb\\x3E')\">",
+            "It's <b>\"bold\"</b></p>",
+        )
+    );
+
+    let tpl = Template::new("<a href={{url}}>{{title}}</a>").unwrap();
+
+    assert_eq!(
+        tpl.render(&Link {
+            url: "  JaVa\tScRiPt:alert(1)",
+            title: "Nope",
+        }),
+        "<a href=about:invalid>Nope</a>"
+    );
+    assert_eq!(
+        tpl.render(&Link {
+            url: "https://example.com/?q=\"a b\"&page=2",
+            title: "Search",
+        }),
+        "<a href=https://example.com/?q=%22a%20b%22&amp;page=2>Search</a>"
+    );
+    assert_eq!(
+        tpl.render(&Link {
+            url: "/posts/10:30",
+            title: "Relative",
+        }),
+        "<a href=/posts/10:30>Relative</a>"
+    );
+
+    let tpl = Template::new("<a href=\"/posts/{{url}}\">{{title}}</a>").unwrap();
+
+    assert_eq!(
+        tpl.render(&Link {
+            url: "javascript:alert(1)",
+            title: "Relative",
+        }),
+        "<a href=\"/posts/javascript:alert(1)\">Relative</a>"
+    );
+}
+
+#[test]
+fn context_aware_escaping_in_partials() {
+    #[derive(Content)]
+    struct Link<'a> {
+        url: &'a str,
+        title: &'a str,
+    }
+
+    let mut tpls: Ramhorns = Ramhorns::lazy("templates").unwrap();
+
+    tpls.insert("{{title}}", "title.html").unwrap();
+    tpls.insert("{{url}}", "url.html").unwrap();
+    tpls.insert(
+        concat!(
+            "<a href=\"{{>url.html}}\" title=\"{{>title.html}}\">{{>title.html}}</a>",
+            "<script>var title = \"{{>title.html}}\";</script>",
+        ),
+        "link.html",
+    )
+    .unwrap();
+
+    let rendered = tpls.get("link.html").unwrap().render(&Link {
+        url: "javascript:alert(1)",
+        title: "It's <b>\"bold\"</b>",
+    });
+
+    assert_eq!(
+        rendered,
+        concat!(
+            "<a href=\"about:invalid\" title=\"It&#x27;s &lt;b&gt;&quot;bold&quot;&lt;/b&gt;\">",
+            "It's &lt;b&gt;&quot;bold&quot;&lt;/b&gt;</a>",
+            "<script>var title = \"It\\x27s\\x20\\x3Cb\\x3E\\x22bold\\x22\\x3C\\x2Fb\\x3E\";</script>",
+        )
+    );
+}
+
+#[test]
+fn escaping_strategies() {
+    use ramhorns::Escape;
+
+    #[derive(Content)]
+    struct Person<'a> {
+        name: &'a str,
+        age: u8,
+    }
+
+    let person = Person {
+        name: "Tom & \"Jerry\" 100%",
+        age: 3,
+    };
+    let mut tpls: Ramhorns = Ramhorns::lazy("escape_templates")
+        .unwrap()
+        .escape_extension("tex", Escape::Latex)
+        .escape_extension("txt", Escape::Html)
+        .escape_extension("txt", Escape::None);
+
+    assert_eq!(
+        tpls.from_file("letter.tex").unwrap().render(&person),
+        "\\textbf{ Tom \\& \"Jerry\" 100\\% }"
+    );
+    assert_eq!(
+        tpls.from_file("notes.txt").unwrap().render(&person),
+        "Hi Tom & \"Jerry\" 100%!"
+    );
+    assert_eq!(
+        tpls.from_file("page.html").unwrap().render(&person),
+        "<b>Tom &amp; &quot;Jerry&quot; 100%</b>"
+    );
+
+    let tpl = Template::new("{\"name\": \"{{name}}\", \"age\": {{age}}}")
+        .unwrap()
+        .with_escape(Escape::Json);
+    let mut buf = Vec::new();
+    tpl.render_to_writer(&mut buf, &person).unwrap();
+
+    assert_eq!(tpl.escape(), Escape::Json);
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "{\"name\": \"Tom & \\\"Jerry\\\" 100%\", \"age\": 3}"
+    );
+    assert_eq!(
+        tpl.render_with_escaper(&person, Escape::Shell),
+        "{\"name\": \"'Tom & \"Jerry\" 100%'\", \"age\": 3}"
+    );
+}
+
+#[test]
+fn strict_escape_table() {
+    use ramhorns::encoding::EscapeTable;
+    use ramhorns::{Escape, Escaping};
+
+    let tpl = Template::new("<p title='{{title}}'>{{title}}</p>{{{body}}}").unwrap();
+    let post = Post {
+        title: "It's `a=b` <3",
+        body: "<b>'raw'</b>",
+    };
+
+    assert_eq!(
+        tpl.render(&post),
+        "<p title='It&#x27;s &#x60;a=b&#x60; &lt;3'>It's `a=b` &lt;3</p><b>'raw'</b>"
+    );
+    assert_eq!(
+        tpl.render_with_escaper(&post, EscapeTable::STRICT),
+        "<p title='It&#x27;s &#x60;a=b&#x60; &lt;3'>It&#x27;s &#x60;a&#x3D;b&#x60; &lt;3</p><b>'raw'</b>"
+    );
+
+    let tpl = tpl.with_escape(Escape::Table(&EscapeTable::STRICT));
+    let mut buf = Vec::new();
+    tpl.render_to_writer(&mut buf, &post).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "<p title='It&#x27;s &#x60;a=b&#x60; &lt;3'>It&#x27;s &#x60;a&#x3D;b&#x60; &lt;3</p><b>'raw'</b>"
+    );
+
+    let table = EscapeTable::empty().with(b'a', "4").without(b'<');
+    let mut buf = String::new();
+    let _ = Template::new("{{title}}")
+        .unwrap()
+        .render_with_encoder(&mut Escaping::new(&mut buf, &table), &post);
+
+    assert_eq!(buf, "It's `4=b` <3");
+    assert_eq!(table.get(b'a'), Some("4"));
+    assert_eq!(EscapeTable::STRICT.get(b'='), Some("&#x3D;"));
+}
+
+#[test]
+fn render_with_display() {
+    use ramhorns::encoding::EscapingFmtEncoder;
+    use ramhorns::Escape;
+    use std::fmt::Write;
+
+    let tpl = Template::new("<h1>{{title}}</h1><div>{{{body}}}</div>").unwrap();
+    let post = Post {
+        title: "Fish & \"Chips\"",
+        body: "<b>tasty</b>",
+    };
+
+    assert_eq!(format!("{}", tpl.bind(&post)), tpl.render(&post));
+    assert_eq!(
+        format!("[{:?}] {}", 1, tpl.bind(&post)),
+        "[1] <h1>Fish &amp; &quot;Chips&quot;</h1><div><b>tasty</b></div>"
+    );
+
+    let mut log = String::from("rendered: ");
+    write!(log, "{}", tpl.bind(&post)).unwrap();
+    assert_eq!(
+        log,
+        "rendered: <h1>Fish &amp; &quot;Chips&quot;</h1><div><b>tasty</b></div>"
+    );
+
+    let mut encoder = EscapingFmtEncoder::new(String::new());
+    tpl.render_with_encoder(&mut encoder, &post).unwrap();
+    assert_eq!(encoder.into_inner(), tpl.render(&post));
+
+    let tpl = tpl.with_escape(Escape::None);
+    assert_eq!(
+        tpl.bind(&post).to_string(),
+        "<h1>Fish & \"Chips\"</h1><div><b>tasty</b></div>"
+    );
+}
+
+#[tokio::test]
+async fn render_to_async_writer() {
+    use ramhorns::encoding::AsyncWriteEncoder;
+    use tokio::io::AsyncReadExt;
+
+    let tpl = Template::new("<h1>{{title}}</h1><div>{{{body}}}</div>").unwrap();
+    let post = Post {
+        title: "Fish & \"Chips\"",
+        body: "<b>tasty</b>",
+    };
+
+    // Small buffer, so the writer has to wait for the reader
+    let (mut client, mut server) = tokio::io::duplex(8);
+
+    let write = async {
+        tpl.render_to_async_writer(&mut client, &post)
+            .await
+            .unwrap();
+        drop(client);
+    };
+    let read = async {
+        let mut rendered = String::new();
+        server.read_to_string(&mut rendered).await.unwrap();
+        rendered
+    };
+    let ((), rendered) = tokio::join!(write, read);
+
+    assert_eq!(rendered, tpl.render(&post));
+
+    let (client, mut server) = tokio::io::duplex(1024);
+    let mut encoder = AsyncWriteEncoder::new(client);
+
+    tpl.render_with_encoder(&mut encoder, &post).unwrap();
+    tpl.render_with_encoder(&mut encoder, &post).unwrap();
+    encoder.flush().await.unwrap();
+    drop(encoder.into_inner());
+
+    let mut rendered = String::new();
+    server.read_to_string(&mut rendered).await.unwrap();
+
+    assert_eq!(rendered, tpl.render(&post).repeat(2));
+
+    // Full buffers are written out while rendering
+    let (client, mut server) = tokio::io::duplex(1024);
+    let mut encoder = AsyncWriteEncoder::with_capacity(client, 16);
+
+    for _ in 0..10 {
+        tpl.render_with_encoder(&mut encoder, &post).unwrap();
+        assert!(encoder.buffered() < 16);
+    }
+    encoder.flush().await.unwrap();
+    drop(encoder.into_inner());
+
+    let mut rendered = String::new();
+    server.read_to_string(&mut rendered).await.unwrap();
+
+    assert_eq!(rendered, tpl.render(&post).repeat(10));
+
+    // What the writer can't take right away waits for `flush`
+    let (client, mut server) = tokio::io::duplex(8);
+    let mut encoder = AsyncWriteEncoder::with_capacity(client, 4);
+
+    tpl.render_with_encoder(&mut encoder, &post).unwrap();
+    assert_eq!(encoder.buffered(), tpl.render(&post).len() - 8);
+
+    let write = async {
+        encoder.flush().await.unwrap();
+        drop(encoder.into_inner());
+    };
+    let read = async {
+        let mut rendered = String::new();
+        server.read_to_string(&mut rendered).await.unwrap();
+        rendered
+    };
+    let ((), rendered) = tokio::join!(write, read);
+
+    assert_eq!(rendered, tpl.render(&post));
+}
+
+#[test]
+fn output_size_limit() {
+    use ramhorns::encoding::{EscapingIOEncoder, LimitError, LimitedEncoder};
+    use ramhorns::Escape;
+
+    #[derive(Content)]
+    struct Page<'a> {
+        title: &'a str,
+        views: u64,
+        #[ramhorns(md)]
+        body: &'a str,
+    }
+
+    let tpl = Template::new("<h1>{{title}}</h1><p>{{views}}</p>{{body}}").unwrap();
+    let page = Page {
+        title: "Fish & Chips",
+        views: 1234567,
+        body: "**Yummy**",
+    };
+    let rendered = tpl.render(&page);
+
+    let mut encoder = LimitedEncoder::new(String::new(), rendered.len());
+    tpl.render_with_encoder(&mut encoder, &page).unwrap();
+    assert_eq!(encoder.written(), rendered.len());
+    assert_eq!(encoder.into_inner(), rendered);
+
+    // Every limit that's too small fails, escapes, numbers and markdown included
+    for limit in 0..rendered.len() {
+        let mut encoder = LimitedEncoder::new(String::new(), limit);
+
+        match tpl.render_with_encoder(&mut encoder, &page) {
+            Err(LimitError::Exceeded { limit: l }) => assert_eq!(l, limit),
+            _ => panic!("Limit of {} bytes not enforced", limit),
+        }
+        assert!(encoder.written() <= limit);
+        assert_eq!(encoder.written(), encoder.into_inner().len());
+    }
+
+    let mut encoder = LimitedEncoder::new(EscapingIOEncoder::new(Vec::new()), 20);
+    let err = tpl.render_with_encoder(&mut encoder, &page).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Rendered output exceeds the limit of 20 bytes"
+    );
+    assert_eq!(
+        String::from_utf8(encoder.into_inner().into_inner()).unwrap(),
+        "<h1>Fish &amp; Chips"
+    );
+
+    // Escapes in other contexts are counted as written, however long
+    let tpl = Template::new("<p title={{title}}>").unwrap();
+    let rendered = tpl.render(&page);
+    let mut encoder = LimitedEncoder::new(String::new(), rendered.len());
+
+    tpl.render_with_encoder(&mut encoder, &page).unwrap();
+    assert_eq!(rendered, "<p title=Fish&#x20;&#x26;&#x20;Chips>");
+    assert_eq!(encoder.written(), rendered.len());
+
+    // Templates with another escape hand over values escaped already
+    let tpl = Template::new("<h1>{{title}}</h1><p>{{views}}</p>{{body}}")
+        .unwrap()
+        .with_escape(Escape::Latex);
+    let rendered = tpl.render(&page);
+    let mut encoder = LimitedEncoder::new(String::new(), rendered.len());
+
+    tpl.render_with_encoder(&mut encoder, &page).unwrap();
+    assert_eq!(encoder.written(), rendered.len());
+    assert_eq!(
+        encoder.into_inner(),
+        "<h1>Fish \\& Chips</h1><p>1234567</p><p><strong>Yummy</strong></p>\n"
+    );
+
+    let mut encoder = LimitedEncoder::new(String::new(), rendered.len() - 1);
+    assert!(tpl.render_with_encoder(&mut encoder, &page).is_err());
+}
+
+#[test]
+fn render_budget() {
+    use ramhorns::encoding::{
+        BudgetEncoder, BudgetError, Encoder, LimitError, LimitedEncoder, RenderBudget,
+    };
+    use ramhorns::traits::ContentSequence;
+    use ramhorns::{Escape, Section};
+    use std::time::{Duration, Instant};
+
+    #[derive(Content, Clone)]
+    struct Item {
+        name: &'static str,
+    }
+
+    #[derive(Content, Clone)]
+    struct List {
+        items: Vec<Item>,
+    }
+
+    #[derive(Content)]
+    struct Lists {
+        lists: Vec<List>,
+    }
+
+    let tpl = Template::new("{{#lists}}<ul>{{#items}}<li>{{name}}</li>{{/items}}</ul>{{/lists}}")
+        .unwrap();
+    let list = List {
+        items: vec![Item { name: "Ramhorns" }; 10],
+    };
+    let lists = Lists {
+        lists: vec![list; 10],
+    };
+    let rendered = tpl.render(&lists);
+
+    let mut encoder = BudgetEncoder::new(String::new(), RenderBudget::new());
+    tpl.render_with_encoder(&mut encoder, &lists).unwrap();
+    let (blocks, iterations) = (encoder.blocks(), encoder.iterations());
+
+    assert_eq!(iterations, 110);
+    assert_eq!(encoder.into_inner(), rendered);
+
+    let budget = RenderBudget::new()
+        .blocks(blocks)
+        .iterations(iterations)
+        .timeout(Duration::from_secs(60));
+    let mut encoder = BudgetEncoder::new(String::new(), budget);
+    tpl.render_with_encoder(&mut encoder, &lists).unwrap();
+    assert_eq!(encoder.into_inner(), rendered);
+
+    let mut encoder = BudgetEncoder::new(String::new(), RenderBudget::new().blocks(blocks - 1));
+    match tpl.render_with_encoder(&mut encoder, &lists) {
+        Err(BudgetError::Blocks { limit }) => assert_eq!(limit, blocks - 1),
+        _ => panic!("Limit of blocks not enforced"),
+    }
+
+    let mut encoder = BudgetEncoder::new(String::new(), RenderBudget::new().iterations(5));
+    let err = tpl.render_with_encoder(&mut encoder, &lists).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Rendering exceeds the limit of 5 section iterations"
+    );
+    assert_eq!(encoder.iterations(), 5);
+
+    let mut encoder =
+        BudgetEncoder::new(String::new(), RenderBudget::new().deadline(Instant::now()));
+    assert!(matches!(
+        tpl.render_with_encoder(&mut encoder, &lists),
+        Err(BudgetError::Deadline)
+    ));
+
+    // Sections rendered by any `Content` are counted, not only built-in collections
+    struct Repeat(usize);
+
+    impl Content for Repeat {
+        fn render_section<C, E>(&self, section: Section<C>, encoder: &mut E) -> Result<(), E::Error>
+        where
+            C: ContentSequence,
+            E: Encoder,
+        {
+            for _ in 0..self.0 {
+                section.render(encoder)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Content)]
+    struct Page {
+        rules: Repeat,
+    }
+
+    let page = Template::new("{{#rules}}<hr>{{/rules}}").unwrap();
+    let mut encoder = BudgetEncoder::new(String::new(), RenderBudget::new().iterations(5));
+    assert!(matches!(
+        page.render_with_encoder(
+            &mut encoder,
+            &Page {
+                rules: Repeat(1000)
+            }
+        ),
+        Err(BudgetError::Iterations { limit: 5 })
+    ));
+    assert_eq!(encoder.into_inner(), "<hr>".repeat(5));
+
+    // Wrapping encoders pass the budget through
+    let tpl = tpl.with_escape(Escape::Json);
+    let budget = BudgetEncoder::new(String::new(), RenderBudget::new().iterations(5));
+    let mut encoder = LimitedEncoder::new(budget, 1 << 20);
+    assert!(matches!(
+        tpl.render_with_encoder(&mut encoder, &lists),
+        Err(LimitError::Encoder(BudgetError::Iterations { limit: 5 }))
+    ));
+}